@@ -0,0 +1,63 @@
+-- Strain data, queried by /search, /strain and /about.
+-- Written to be idempotent, so it also applies cleanly to databases created before migrations existed.
+
+DO $$
+BEGIN
+    CREATE TYPE public.subspecies AS ENUM ('hybrid', 'indica', 'sativa', 'ruderalis');
+EXCEPTION
+    WHEN duplicate_object THEN NULL;
+END
+$$;
+
+CREATE TABLE IF NOT EXISTS public.strains (
+    id BIGSERIAL PRIMARY KEY,
+    name TEXT NOT NULL,
+    description TEXT,
+    subspecies public.subspecies,
+    image_url TEXT
+);
+
+CREATE INDEX IF NOT EXISTS strains_subspecies_idx ON public.strains (subspecies);
+
+-- Effects
+CREATE TABLE IF NOT EXISTS public.unique_effects (
+    id SERIAL PRIMARY KEY,
+    effect TEXT NOT NULL UNIQUE,
+    is_positive BOOLEAN NOT NULL DEFAULT TRUE
+);
+
+CREATE TABLE IF NOT EXISTS public.strain_effects (
+    strain_id BIGINT NOT NULL REFERENCES public.strains (id) ON DELETE CASCADE,
+    effect_id INTEGER NOT NULL REFERENCES public.unique_effects (id) ON DELETE CASCADE,
+    PRIMARY KEY (strain_id, effect_id)
+);
+
+CREATE INDEX IF NOT EXISTS strain_effects_effect_id_idx ON public.strain_effects (effect_id);
+
+-- Flavors
+CREATE TABLE IF NOT EXISTS public.unique_flavors (
+    id SERIAL PRIMARY KEY,
+    flavor TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS public.strain_flavors (
+    strain_id BIGINT NOT NULL REFERENCES public.strains (id) ON DELETE CASCADE,
+    flavor_id INTEGER NOT NULL REFERENCES public.unique_flavors (id) ON DELETE CASCADE,
+    PRIMARY KEY (strain_id, flavor_id)
+);
+
+CREATE INDEX IF NOT EXISTS strain_flavors_flavor_id_idx ON public.strain_flavors (flavor_id);
+
+-- Ailments
+CREATE TABLE IF NOT EXISTS public.unique_ailments (
+    id SERIAL PRIMARY KEY,
+    ailment TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS public.strain_ailments (
+    strain_id BIGINT NOT NULL REFERENCES public.strains (id) ON DELETE CASCADE,
+    ailment_id INTEGER NOT NULL REFERENCES public.unique_ailments (id) ON DELETE CASCADE,
+    PRIMARY KEY (strain_id, ailment_id)
+);
+
+CREATE INDEX IF NOT EXISTS strain_ailments_ailment_id_idx ON public.strain_ailments (ailment_id);
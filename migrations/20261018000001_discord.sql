@@ -0,0 +1,17 @@
+-- Discord users and guilds, used for puffs and blacklisting.
+-- Snowflake IDs are unsigned 64-bit integers, so they are stored as NUMERIC.
+-- Written to be idempotent, so it also applies cleanly to databases created before migrations existed.
+
+CREATE SCHEMA IF NOT EXISTS discord;
+
+CREATE TABLE IF NOT EXISTS discord.users (
+    user_id NUMERIC(20, 0) PRIMARY KEY,
+    puffs BIGINT NOT NULL DEFAULT 0,
+    is_blacklisted BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE TABLE IF NOT EXISTS discord.guilds (
+    guild_id NUMERIC(20, 0) PRIMARY KEY,
+    puffs BIGINT NOT NULL DEFAULT 0,
+    is_blacklisted BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1,5 @@
+-- Typo-tolerant strain name search for /search and name autocomplete
+
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+CREATE INDEX IF NOT EXISTS strains_name_trgm_idx ON public.strains USING GIN (name gin_trgm_ops);
@@ -0,0 +1,9 @@
+-- Scheduled strain of the day posts, configured with /config daily-strain.
+-- Times are local to the guild's IANA timezone (see pg_timezone_names).
+
+ALTER TABLE discord.guilds
+    ADD COLUMN daily_strain_channel_id NUMERIC(20, 0),
+    ADD COLUMN daily_strain_time TIME NOT NULL DEFAULT '16:20',
+    ADD COLUMN daily_strain_timezone TEXT NOT NULL DEFAULT 'UTC',
+    -- Local date of the last post, so each guild gets one post per day
+    ADD COLUMN daily_strain_last_posted DATE;
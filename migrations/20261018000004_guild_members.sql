@@ -0,0 +1,11 @@
+-- Puffs each member took in each guild, for per-server member leaderboards
+
+CREATE TABLE discord.guild_members (
+    guild_id NUMERIC(20, 0) NOT NULL REFERENCES discord.guilds (guild_id) ON DELETE CASCADE,
+    user_id NUMERIC(20, 0) NOT NULL REFERENCES discord.users (user_id) ON DELETE CASCADE,
+    puffs BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (guild_id, user_id)
+);
+
+CREATE INDEX guild_members_user_id_idx ON discord.guild_members (user_id);
+CREATE INDEX guild_members_puffs_idx ON discord.guild_members (guild_id, puffs DESC);
@@ -0,0 +1,11 @@
+-- Every puff taken, for /puff history
+
+CREATE TABLE discord.puff_events (
+    id BIGSERIAL PRIMARY KEY,
+    user_id NUMERIC(20, 0) NOT NULL REFERENCES discord.users (user_id) ON DELETE CASCADE,
+    -- NULL when taken in DMs
+    guild_id NUMERIC(20, 0) REFERENCES discord.guilds (guild_id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX puff_events_user_id_created_at_idx ON discord.puff_events (user_id, created_at);
@@ -0,0 +1,14 @@
+-- Daily puff streaks (by the guild's local date, UTC in DMs) and achievements
+
+ALTER TABLE discord.users
+    ADD COLUMN current_streak INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN longest_streak INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN last_puff_date DATE;
+
+CREATE TABLE discord.user_achievements (
+    user_id NUMERIC(20, 0) NOT NULL REFERENCES discord.users (user_id) ON DELETE CASCADE,
+    -- Matches `Achievement::id`
+    achievement TEXT NOT NULL,
+    earned_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, achievement)
+);
@@ -0,0 +1,6 @@
+-- Per guild /puff settings, configured with /config puff
+
+ALTER TABLE discord.guilds
+    ADD COLUMN puffs_enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    -- Seconds each member has to wait between /puff take
+    ADD COLUMN puff_cooldown INTEGER NOT NULL DEFAULT 30 CHECK (puff_cooldown >= 0);
@@ -0,0 +1,14 @@
+-- Puff counts from /puff reset, kept so /puff undo-reset can restore them until they expire
+
+CREATE TABLE discord.puff_resets (
+    id BIGSERIAL PRIMARY KEY,
+    user_id NUMERIC(20, 0) NOT NULL REFERENCES discord.users (user_id) ON DELETE CASCADE,
+    -- Guild whose member count was reset, or NULL for the global count
+    guild_id NUMERIC(20, 0),
+    puffs BIGINT NOT NULL,
+    reset_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX puff_resets_user_id_idx ON discord.puff_resets (user_id);
+CREATE INDEX puff_resets_expires_at_idx ON discord.puff_resets (expires_at);
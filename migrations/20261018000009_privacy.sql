@@ -0,0 +1,18 @@
+-- Users who opted out with /privacy opt-out never get a discord.users row,
+-- and every /privacy deletion or opt-out is kept in an audit log
+
+CREATE TABLE discord.opted_out_users (
+    user_id NUMERIC(20, 0) PRIMARY KEY,
+    opted_out_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE discord.privacy_audit_log (
+    id BIGSERIAL PRIMARY KEY,
+    -- Not a foreign key, entries outlive the user's data
+    user_id NUMERIC(20, 0) NOT NULL,
+    -- Matches `PrivacyAction::id`
+    action TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX privacy_audit_log_user_id_idx ON discord.privacy_audit_log (user_id);
@@ -0,0 +1,7 @@
+-- When the bot was removed from a guild. Its data is purged once the grace period
+-- passes (see scheduler.rs), unless it's re-added or blacklisted first.
+
+ALTER TABLE discord.guilds
+    ADD COLUMN removed_at TIMESTAMPTZ;
+
+CREATE INDEX guilds_removed_at_idx ON discord.guilds (removed_at) WHERE removed_at IS NOT NULL;
@@ -0,0 +1,14 @@
+-- Why, when and by which owner a user or guild was blacklisted with /blacklist.
+-- Blacklists with an expiry are lifted by the prune task once it passes.
+
+ALTER TABLE discord.users
+    ADD COLUMN blacklist_reason TEXT,
+    ADD COLUMN blacklisted_by NUMERIC(20, 0),
+    ADD COLUMN blacklisted_at TIMESTAMPTZ,
+    ADD COLUMN blacklist_expires_at TIMESTAMPTZ;
+
+ALTER TABLE discord.guilds
+    ADD COLUMN blacklist_reason TEXT,
+    ADD COLUMN blacklisted_by NUMERIC(20, 0),
+    ADD COLUMN blacklisted_at TIMESTAMPTZ,
+    ADD COLUMN blacklist_expires_at TIMESTAMPTZ;
@@ -0,0 +1,7 @@
+-- The strain of the day picked for each (UTC) date, so importing or removing strains
+-- doesn't change a pick that's already been shown.
+
+CREATE TABLE public.daily_strains (
+    day DATE PRIMARY KEY,
+    strain_id BIGINT NOT NULL REFERENCES public.strains (id) ON DELETE CASCADE
+);
@@ -0,0 +1,10 @@
+-- Server reset snapshots go when their guild's data is purged, like its member puffs
+
+DELETE FROM discord.puff_resets r
+WHERE
+    r.guild_id IS NOT NULL
+    AND NOT EXISTS (SELECT 1 FROM discord.guilds g WHERE g.guild_id = r.guild_id);
+
+ALTER TABLE discord.puff_resets
+    ADD CONSTRAINT puff_resets_guild_id_fkey
+    FOREIGN KEY (guild_id) REFERENCES discord.guilds (guild_id) ON DELETE CASCADE;
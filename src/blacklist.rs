@@ -0,0 +1,278 @@
+//! Cached blacklist lookups for the global command check.
+//!
+//! Entries expire after [`TTL`], or when a temporary blacklist ends if that's sooner, and
+//! `/admin` invalidates the user or guild it changes, so most commands never hit the database
+//! for the check.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use poise::serenity_prelude::{GuildId, UserId};
+use sqlx::{
+    types::{time::OffsetDateTime, BigDecimal},
+    PgPool,
+};
+use tracing::warn;
+
+use crate::types::CheckError;
+
+/// Longest a lookup is cached for
+const TTL: Duration = Duration::from_secs(5 * 60);
+/// Most users (and separately guilds) cached at once
+const CAPACITY: usize = 10_000;
+
+/// What the global command check does when the blacklist can't be read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Let the command run
+    Open,
+    /// Refuse the command
+    Closed,
+}
+
+impl FailurePolicy {
+    /// Reads `BLACKLIST_FAILURE_POLICY` (`open` or `closed`), defaulting to open
+    pub fn from_env() -> Self {
+        match std::env::var("BLACKLIST_FAILURE_POLICY") {
+            Ok(value) if value.eq_ignore_ascii_case("closed") => Self::Closed,
+            Ok(value) if value.eq_ignore_ascii_case("open") => Self::Open,
+            Ok(value) => {
+                warn!("Unknown BLACKLIST_FAILURE_POLICY `{value}`, failing open");
+                Self::Open
+            }
+            Err(_) => Self::Open,
+        }
+    }
+}
+
+/// How long a lookup can be cached for, ending early when a temporary blacklist expires
+fn lifetime(is_blacklisted: bool, expires_at: Option<OffsetDateTime>) -> Duration {
+    match expires_at {
+        Some(expires_at) if is_blacklisted => (expires_at - OffsetDateTime::now_utc())
+            .try_into()
+            .unwrap_or(Duration::ZERO)
+            .min(TTL),
+        _ => TTL,
+    }
+}
+
+/// Cached values, each with the instant it stops being valid
+#[derive(Debug)]
+struct TtlMap<K> {
+    inner: Mutex<TtlMapInner<K>>,
+}
+
+#[derive(Debug)]
+struct TtlMapInner<K> {
+    entries: HashMap<K, (bool, Instant)>,
+    /// Bumped on every removal, so lookups started before it can't cache what they read
+    generation: u64,
+}
+
+impl<K: Hash + Eq + Copy> TtlMap<K> {
+    fn new() -> Self {
+        Self {
+            inner: Mutex::new(TtlMapInner {
+                entries: HashMap::new(),
+                generation: 0,
+            }),
+        }
+    }
+
+    fn get(&self, key: &K) -> Option<bool> {
+        let inner = self.inner.lock().unwrap();
+        inner
+            .entries
+            .get(key)
+            .filter(|(_, expires)| *expires > Instant::now())
+            .map(|(value, _)| *value)
+    }
+
+    /// Taken before reading the database, and passed back to [`TtlMap::insert`]
+    fn generation(&self) -> u64 {
+        self.inner.lock().unwrap().generation
+    }
+
+    /// Caches the value, unless something was removed since `generation` was taken,
+    /// as the value may have been read before that change
+    fn insert(&self, key: K, value: bool, lifetime: Duration, generation: u64) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.generation != generation {
+            return;
+        }
+
+        let entries = &mut inner.entries;
+        if entries.len() >= CAPACITY && !entries.contains_key(&key) {
+            let now = Instant::now();
+            entries.retain(|_, (_, expires)| *expires > now);
+
+            // Still mostly live entries, so drop the ones expiring soonest in one go,
+            // leaving room for many inserts before the next scan
+            let target = CAPACITY - CAPACITY / 10;
+            if entries.len() > target {
+                let mut expiries = entries
+                    .values()
+                    .map(|(_, expires)| *expires)
+                    .collect::<Vec<_>>();
+                let (_, cutoff, _) = expiries.select_nth_unstable(entries.len() - target - 1);
+                let cutoff = *cutoff;
+                entries.retain(|_, (_, expires)| *expires > cutoff);
+            }
+        }
+
+        entries.insert(key, (value, Instant::now() + lifetime));
+    }
+
+    fn remove(&self, key: &K) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.entries.remove(key);
+        inner.generation += 1;
+    }
+}
+
+#[derive(Debug)]
+pub struct BlacklistCache {
+    users: TtlMap<UserId>,
+    guilds: TtlMap<GuildId>,
+    pub policy: FailurePolicy,
+}
+
+impl BlacklistCache {
+    pub fn new(policy: FailurePolicy) -> Self {
+        Self {
+            users: TtlMap::new(),
+            guilds: TtlMap::new(),
+            policy,
+        }
+    }
+
+    pub async fn is_user_blacklisted(
+        &self,
+        pool: &PgPool,
+        user_id: &UserId,
+    ) -> Result<bool, sqlx::Error> {
+        if let Some(is_blacklisted) = self.users.get(user_id) {
+            return Ok(is_blacklisted);
+        }
+        let generation = self.users.generation();
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                is_blacklisted
+                AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()) AS "is_blacklisted!",
+                blacklist_expires_at
+            FROM
+                discord.users
+            WHERE
+                user_id = $1;"#,
+            BigDecimal::from(user_id.get())
+        )
+        .fetch_optional(pool)
+        .await?;
+        let is_blacklisted = row.as_ref().is_some_and(|r| r.is_blacklisted);
+        let expires_at = row.and_then(|r| r.blacklist_expires_at);
+
+        self.users.insert(
+            *user_id,
+            is_blacklisted,
+            lifetime(is_blacklisted, expires_at),
+            generation,
+        );
+        Ok(is_blacklisted)
+    }
+
+    pub async fn is_guild_blacklisted(
+        &self,
+        pool: &PgPool,
+        guild_id: &GuildId,
+    ) -> Result<bool, sqlx::Error> {
+        if let Some(is_blacklisted) = self.guilds.get(guild_id) {
+            return Ok(is_blacklisted);
+        }
+        let generation = self.guilds.generation();
+
+        let row = sqlx::query!(
+            r#"
+            SELECT
+                is_blacklisted
+                AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()) AS "is_blacklisted!",
+                blacklist_expires_at
+            FROM
+                discord.guilds
+            WHERE
+                guild_id = $1;"#,
+            BigDecimal::from(guild_id.get())
+        )
+        .fetch_optional(pool)
+        .await?;
+        let is_blacklisted = row.as_ref().is_some_and(|r| r.is_blacklisted);
+        let expires_at = row.and_then(|r| r.blacklist_expires_at);
+
+        self.guilds.insert(
+            *guild_id,
+            is_blacklisted,
+            lifetime(is_blacklisted, expires_at),
+            generation,
+        );
+        Ok(is_blacklisted)
+    }
+
+    /// Applies the [`FailurePolicy`] to a lookup that may have failed
+    pub fn resolve(&self, lookup: Result<bool, sqlx::Error>) -> Result<bool, CheckError> {
+        match lookup {
+            Ok(is_blacklisted) => Ok(is_blacklisted),
+            Err(error) => {
+                warn!("Failed to check blacklist: {}", error);
+                match self.policy {
+                    FailurePolicy::Open => Ok(false),
+                    FailurePolicy::Closed => Err(CheckError::BlacklistUnavailable),
+                }
+            }
+        }
+    }
+
+    pub fn invalidate_user(&self, user_id: &UserId) {
+        self.users.remove(user_id);
+    }
+
+    pub fn invalidate_guild(&self, guild_id: &GuildId) {
+        self.guilds.remove(guild_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn skips_inserts_read_before_a_removal() {
+        let map = TtlMap::new();
+        let generation = map.generation();
+        map.remove(&1);
+        map.insert(1, false, TTL, generation);
+        assert_eq!(map.get(&1), None);
+
+        map.insert(1, true, TTL, map.generation());
+        assert_eq!(map.get(&1), Some(true));
+    }
+
+    #[test]
+    fn evicts_soonest_expiring_when_full() {
+        let map = TtlMap::new();
+        for key in 0..CAPACITY {
+            map.insert(key, false, TTL + Duration::from_secs(key as u64), 0);
+        }
+        map.insert(CAPACITY, true, TTL * 2, 0);
+
+        let len = map.inner.lock().unwrap().entries.len();
+        assert!(len <= CAPACITY - CAPACITY / 10 + 1);
+        assert_eq!(map.get(&0), None);
+        assert_eq!(map.get(&(CAPACITY - 1)), Some(false));
+        assert_eq!(map.get(&CAPACITY), Some(true));
+    }
+}
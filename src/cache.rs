@@ -0,0 +1,110 @@
+//! Lazily filled caches for data that rarely changes, like the autocomplete lists.
+//!
+//! A value older than its TTL is still served while it's refreshed in the background, and failed
+//! fetches are retried instead of being cached.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use poise::BoxFuture;
+use sqlx::PgPool;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+/// Attempts made by a background refresh before giving up until the next lookup
+const REFRESH_ATTEMPTS: u32 = 3;
+/// Delay before the first retry, doubled after each attempt
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+pub type Fetch<T> = fn(PgPool) -> BoxFuture<'static, Result<T, sqlx::Error>>;
+
+pub struct RefreshCache<T> {
+    name: &'static str,
+    ttl: Duration,
+    fetch: Fetch<T>,
+    value: RwLock<Option<(Arc<T>, Instant)>>,
+    refreshing: AtomicBool,
+}
+
+impl<T: Send + Sync + 'static> RefreshCache<T> {
+    pub const fn new(name: &'static str, ttl: Duration, fetch: Fetch<T>) -> Self {
+        Self {
+            name,
+            ttl,
+            fetch,
+            value: RwLock::const_new(None),
+            refreshing: AtomicBool::new(false),
+        }
+    }
+
+    /// The cached value, fetching it first if there isn't one yet.
+    ///
+    /// Returns `None` only if nothing is cached and the fetch failed
+    pub async fn get(&'static self, pool: &PgPool) -> Option<Arc<T>> {
+        if let Some((value, fetched_at)) = self.value.read().await.as_ref() {
+            if fetched_at.elapsed() >= self.ttl {
+                self.refresh_in_background(pool.clone());
+            }
+            return Some(value.clone());
+        }
+
+        match (self.fetch)(pool.clone()).await {
+            Ok(value) => Some(self.store(value).await),
+            Err(error) => {
+                warn!("Failed to fetch {} cache: {}", self.name, error);
+                None
+            }
+        }
+    }
+
+    /// Drops the cached value, so the next lookup fetches it again
+    pub async fn invalidate(&self) {
+        *self.value.write().await = None;
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    async fn store(&self, value: T) -> Arc<T> {
+        let value = Arc::new(value);
+        *self.value.write().await = Some((value.clone(), Instant::now()));
+        debug!("Refreshed {} cache", self.name);
+        value
+    }
+
+    fn refresh_in_background(&'static self, pool: PgPool) {
+        // Only one refresh at a time
+        if self.refreshing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        tokio::spawn(async move {
+            let mut delay = RETRY_DELAY;
+            for attempt in 1..=REFRESH_ATTEMPTS {
+                match (self.fetch)(pool.clone()).await {
+                    Ok(value) => {
+                        self.store(value).await;
+                        break;
+                    }
+                    Err(error) => {
+                        warn!(
+                            "Failed to refresh {} cache (attempt {}/{}): {}",
+                            self.name, attempt, REFRESH_ATTEMPTS, error
+                        );
+                        if attempt < REFRESH_ATTEMPTS {
+                            tokio::time::sleep(delay).await;
+                            delay *= 2;
+                        }
+                    }
+                }
+            }
+            self.refreshing.store(false, Ordering::Release);
+        });
+    }
+}
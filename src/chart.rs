@@ -0,0 +1,153 @@
+//! Minimal bar chart renderer, drawn straight into a PNG without any external services or fonts
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 400;
+
+const MARGIN_LEFT: u32 = 70;
+const MARGIN_RIGHT: u32 = 20;
+const MARGIN_TOP: u32 = 20;
+const MARGIN_BOTTOM: u32 = 40;
+
+/// Discord's dark theme embed background
+const BACKGROUND: [u8; 3] = [0x2b, 0x2d, 0x31];
+const GRID: [u8; 3] = [0x40, 0x42, 0x49];
+const TEXT: [u8; 3] = [0xdb, 0xde, 0xe1];
+/// Matches `Color::PURPLE` used by the embeds
+const BAR: [u8; 3] = [0x9b, 0x59, 0xb6];
+
+/// Each glyph pixel is drawn as a `TEXT_SCALE`x`TEXT_SCALE` square
+const TEXT_SCALE: u32 = 3;
+
+/// 3x5 digit glyphs, one row per entry with the leftmost pixel as the highest bit
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+pub struct Bar {
+    /// Shown under the bar, only digits are drawn
+    pub label: String,
+    pub value: i64,
+}
+
+struct Canvas {
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn new() -> Self {
+        Self {
+            pixels: BACKGROUND.repeat((WIDTH * HEIGHT) as usize),
+        }
+    }
+
+    fn fill_rect(&mut self, x: u32, y: u32, width: u32, height: u32, color: [u8; 3]) {
+        for row in y..(y + height).min(HEIGHT) {
+            for column in x..(x + width).min(WIDTH) {
+                let index = ((row * WIDTH + column) * 3) as usize;
+                self.pixels[index..index + 3].copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn text_width(text: &str) -> u32 {
+        let glyphs = text.chars().filter(char::is_ascii_digit).count() as u32;
+        (glyphs * 4).saturating_sub(1) * TEXT_SCALE
+    }
+
+    /// Draws the digits in `text`, anything else is skipped
+    fn draw_text(&mut self, x: u32, y: u32, text: &str) {
+        let digits = text.chars().filter_map(|c| c.to_digit(10));
+        for (i, digit) in digits.enumerate() {
+            let glyph_x = x + i as u32 * 4 * TEXT_SCALE;
+            for (row, bits) in DIGITS[digit as usize].iter().enumerate() {
+                for column in 0..3 {
+                    if bits & (0b100 >> column) != 0 {
+                        self.fill_rect(
+                            glyph_x + column * TEXT_SCALE,
+                            y + row as u32 * TEXT_SCALE,
+                            TEXT_SCALE,
+                            TEXT_SCALE,
+                            TEXT,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    fn encode(self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, WIDTH, HEIGHT);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.pixels)?;
+        writer.finish()?;
+        Ok(png)
+    }
+}
+
+/// Rounds the largest value up so the four grid lines land on whole numbers
+fn axis_max(values: impl Iterator<Item = i64>) -> i64 {
+    let max = values.max().unwrap_or(0).max(4);
+    (max + 3) / 4 * 4
+}
+
+/// Renders a bar chart as a PNG
+pub fn bar_chart(bars: &[Bar]) -> Result<Vec<u8>, png::EncodingError> {
+    let mut canvas = Canvas::new();
+
+    let plot_width = WIDTH - MARGIN_LEFT - MARGIN_RIGHT;
+    let plot_height = HEIGHT - MARGIN_TOP - MARGIN_BOTTOM;
+    let baseline = MARGIN_TOP + plot_height;
+    let max = axis_max(bars.iter().map(|bar| bar.value));
+
+    // Grid lines and y axis labels
+    for step in 0..=4 {
+        let value = max * step / 4;
+        let y = baseline - (plot_height as i64 * step / 4) as u32;
+        canvas.fill_rect(MARGIN_LEFT, y, plot_width, 1, GRID);
+
+        let label = value.to_string();
+        let label_x = MARGIN_LEFT.saturating_sub(Canvas::text_width(&label) + 10);
+        canvas.draw_text(label_x, y.saturating_sub(2 * TEXT_SCALE), &label);
+    }
+
+    if bars.is_empty() {
+        return canvas.encode();
+    }
+
+    let slot = plot_width / bars.len() as u32;
+    let bar_width = (slot * 7 / 10).max(1);
+    // Skip x labels when they'd overlap
+    let label_every = bars
+        .iter()
+        .map(|bar| Canvas::text_width(&bar.label) + 2 * TEXT_SCALE)
+        .max()
+        .unwrap_or(1)
+        .div_ceil(slot.max(1))
+        .max(1) as usize;
+
+    for (i, bar) in bars.iter().enumerate() {
+        let x = MARGIN_LEFT + i as u32 * slot + (slot - bar_width) / 2;
+        let height = (plot_height as i64 * bar.value.max(0) / max) as u32;
+        canvas.fill_rect(x, baseline - height, bar_width, height, BAR);
+
+        if i % label_every == 0 {
+            let label_x = (x + bar_width / 2).saturating_sub(Canvas::text_width(&bar.label) / 2);
+            canvas.draw_text(label_x, baseline + 3 * TEXT_SCALE, &bar.label);
+        }
+    }
+
+    canvas.encode()
+}
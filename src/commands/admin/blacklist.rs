@@ -0,0 +1,574 @@
+use std::time::Duration;
+
+use poise::{
+    serenity_prelude::{Color, CreateEmbed, CreateEmbedFooter, GuildId, User, UserId},
+    CreateReply,
+};
+use sqlx::{types::BigDecimal, PgPool};
+
+use crate::{
+    error_reply,
+    pagination::{page_count, paginate, Page},
+    types::{CommandError, CommandResult, Context},
+};
+
+/// Entries shown per page of the blacklist
+const PAGE_SIZE: i64 = 10;
+
+/// Longest temporary blacklist, anything longer should be permanent
+const MAX_EXPIRY: Duration = Duration::from_secs(10 * 365 * 24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Target {
+    User,
+    Guild,
+}
+
+impl Target {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::User => "User",
+            Self::Guild => "Guild",
+        }
+    }
+}
+
+struct BlacklistEntry {
+    /// User or guild ID
+    id: BigDecimal,
+    reason: Option<String>,
+    blacklisted_by: Option<BigDecimal>,
+    blacklisted_at: Option<i64>,
+    expires_at: Option<i64>,
+}
+
+/// Parses a duration like `30m`, `12h`, `7d` or `2w`, up to [`MAX_EXPIRY`]
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (amount, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit())?);
+    let seconds = match unit.trim() {
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    let duration = Duration::from_secs(amount.parse::<u64>().ok()?.checked_mul(seconds)?);
+    (!duration.is_zero() && duration <= MAX_EXPIRY).then_some(duration)
+}
+
+/// Blacklists the user or guild, replacing any existing reason and expiry.
+///
+/// Returns when the blacklist expires, as a unix timestamp
+async fn add(
+    pool: &PgPool,
+    target: Target,
+    id: u64,
+    reason: &str,
+    owner: &UserId,
+    expires_in: Option<Duration>,
+) -> Result<Option<i64>, sqlx::Error> {
+    let id = BigDecimal::from(id);
+    let owner = BigDecimal::from(owner.get());
+    let expires_in = expires_in.map(|d| d.as_secs_f64());
+
+    // Inserted directly instead of with `insert_user`, as opted out users can still be blacklisted
+    match target {
+        Target::User => {
+            sqlx::query_scalar!(
+                r#"
+            INSERT INTO discord.users (
+                user_id,
+                is_blacklisted,
+                blacklist_reason,
+                blacklisted_by,
+                blacklisted_at,
+                blacklist_expires_at
+            )
+            VALUES ($1, TRUE, $2, $3, NOW(), NOW() + make_interval(secs => $4))
+            ON CONFLICT (user_id) DO UPDATE
+            SET
+                is_blacklisted = TRUE,
+                blacklist_reason = EXCLUDED.blacklist_reason,
+                blacklisted_by = EXCLUDED.blacklisted_by,
+                blacklisted_at = EXCLUDED.blacklisted_at,
+                blacklist_expires_at = EXCLUDED.blacklist_expires_at
+            RETURNING EXTRACT(EPOCH FROM blacklist_expires_at)::BIGINT;"#,
+                id,
+                reason,
+                owner,
+                expires_in
+            )
+            .fetch_one(pool)
+            .await
+        }
+        Target::Guild => {
+            sqlx::query_scalar!(
+                r#"
+            INSERT INTO discord.guilds (
+                guild_id,
+                is_blacklisted,
+                blacklist_reason,
+                blacklisted_by,
+                blacklisted_at,
+                blacklist_expires_at
+            )
+            VALUES ($1, TRUE, $2, $3, NOW(), NOW() + make_interval(secs => $4))
+            ON CONFLICT (guild_id) DO UPDATE
+            SET
+                is_blacklisted = TRUE,
+                blacklist_reason = EXCLUDED.blacklist_reason,
+                blacklisted_by = EXCLUDED.blacklisted_by,
+                blacklisted_at = EXCLUDED.blacklisted_at,
+                blacklist_expires_at = EXCLUDED.blacklist_expires_at
+            RETURNING EXTRACT(EPOCH FROM blacklist_expires_at)::BIGINT;"#,
+                id,
+                reason,
+                owner,
+                expires_in
+            )
+            .fetch_one(pool)
+            .await
+        }
+    }
+}
+
+/// Lifts the blacklist, returning whether the user or guild was blacklisted
+async fn remove(pool: &PgPool, target: Target, id: u64) -> Result<bool, sqlx::Error> {
+    let id = BigDecimal::from(id);
+
+    let result = match target {
+        Target::User => {
+            sqlx::query!(
+                r#"
+                UPDATE discord.users
+                SET
+                    is_blacklisted = FALSE,
+                    blacklist_reason = NULL,
+                    blacklisted_by = NULL,
+                    blacklisted_at = NULL,
+                    blacklist_expires_at = NULL
+                WHERE user_id = $1 AND is_blacklisted;"#,
+                id
+            )
+            .execute(pool)
+            .await?
+        }
+        Target::Guild => {
+            sqlx::query!(
+                r#"
+                UPDATE discord.guilds
+                SET
+                    is_blacklisted = FALSE,
+                    blacklist_reason = NULL,
+                    blacklisted_by = NULL,
+                    blacklisted_at = NULL,
+                    blacklist_expires_at = NULL
+                WHERE guild_id = $1 AND is_blacklisted;"#,
+                id
+            )
+            .execute(pool)
+            .await?
+        }
+    };
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// One page of blacklisted users or guilds, newest first, along with the total
+async fn fetch_page(
+    pool: &PgPool,
+    target: Target,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<BlacklistEntry>, i64), sqlx::Error> {
+    let rows = match target {
+        Target::User => sqlx::query!(
+            r#"
+            SELECT
+                user_id AS id,
+                blacklist_reason,
+                blacklisted_by,
+                EXTRACT(EPOCH FROM blacklisted_at)::BIGINT AS blacklisted_at,
+                EXTRACT(EPOCH FROM blacklist_expires_at)::BIGINT AS expires_at,
+                COUNT(*) OVER () AS "total!"
+            FROM discord.users
+            WHERE is_blacklisted
+            ORDER BY blacklisted_at DESC NULLS LAST, user_id ASC
+            LIMIT $1
+            OFFSET $2;"#,
+            limit,
+            offset
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            (
+                BlacklistEntry {
+                    id: r.id,
+                    reason: r.blacklist_reason,
+                    blacklisted_by: r.blacklisted_by,
+                    blacklisted_at: r.blacklisted_at,
+                    expires_at: r.expires_at,
+                },
+                r.total,
+            )
+        })
+        .collect::<Vec<_>>(),
+        Target::Guild => sqlx::query!(
+            r#"
+            SELECT
+                guild_id AS id,
+                blacklist_reason,
+                blacklisted_by,
+                EXTRACT(EPOCH FROM blacklisted_at)::BIGINT AS blacklisted_at,
+                EXTRACT(EPOCH FROM blacklist_expires_at)::BIGINT AS expires_at,
+                COUNT(*) OVER () AS "total!"
+            FROM discord.guilds
+            WHERE is_blacklisted
+            ORDER BY blacklisted_at DESC NULLS LAST, guild_id ASC
+            LIMIT $1
+            OFFSET $2;"#,
+            limit,
+            offset
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            (
+                BlacklistEntry {
+                    id: r.id,
+                    reason: r.blacklist_reason,
+                    blacklisted_by: r.blacklisted_by,
+                    blacklisted_at: r.blacklisted_at,
+                    expires_at: r.expires_at,
+                },
+                r.total,
+            )
+        })
+        .collect::<Vec<_>>(),
+    };
+
+    let total = rows.first().map(|(_, total)| *total).unwrap_or(0);
+    Ok((rows.into_iter().map(|(entry, _)| entry).collect(), total))
+}
+
+/// Owner only bot administration
+#[poise::command(
+    slash_command,
+    category = "Admin",
+    owners_only,
+    hide_in_help,
+    default_member_permissions = "ADMINISTRATOR",
+    subcommands("blacklist_user", "blacklist_guild"),
+    subcommand_required
+)]
+pub async fn admin(_ctx: Context<'_>) -> CommandResult {
+    // This command cannot be called by the client because of Discord's subcommand system.
+    // Groups can't be nested either, hence `/admin blacklist-user add` over `/admin blacklist user add`.
+    // See https://discord.com/developers/docs/interactions/application-commands#subcommands-and-subcommand-groups
+    Ok(())
+}
+
+/// Manage blacklisted users
+#[poise::command(
+    slash_command,
+    rename = "blacklist-user",
+    subcommands("blacklist_user_add", "blacklist_user_remove", "blacklist_user_list"),
+    subcommand_required
+)]
+pub async fn blacklist_user(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Manage blacklisted guilds
+#[poise::command(
+    slash_command,
+    rename = "blacklist-guild",
+    subcommands(
+        "blacklist_guild_add",
+        "blacklist_guild_remove",
+        "blacklist_guild_list"
+    ),
+    subcommand_required
+)]
+pub async fn blacklist_guild(_ctx: Context<'_>) -> CommandResult {
+    Ok(())
+}
+
+/// Parses a guild ID option, replying with an error if it isn't one
+async fn parse_guild_id(ctx: Context<'_>, guild_id: &str) -> Result<Option<u64>, CommandError> {
+    match guild_id.trim().parse::<u64>() {
+        Ok(id) if id != 0 => Ok(Some(id)),
+        _ => {
+            ctx.send(error_reply(
+                "Invalid Guild ID",
+                Some(&format!("`{guild_id}` isn't a guild ID")),
+            ))
+            .await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Drops the cached lookup, so the change applies to the next command
+fn invalidate(ctx: Context<'_>, target: Target, id: u64) {
+    let blacklist = &ctx.data().blacklist;
+    match target {
+        Target::User => blacklist.invalidate_user(&UserId::new(id)),
+        Target::Guild => blacklist.invalidate_guild(&GuildId::new(id)),
+    }
+}
+
+async fn add_command(
+    ctx: Context<'_>,
+    target: Target,
+    id: u64,
+    label: String,
+    reason: String,
+    expires_in: Option<String>,
+) -> CommandResult {
+    let expires_in = match expires_in.as_deref().map(parse_duration) {
+        Some(None) => {
+            ctx.send(error_reply(
+                "Invalid Expiry",
+                Some("Expiries look like `30m`, `12h`, `7d` or `2w`, up to 10 years. Leave it empty for a permanent blacklist"),
+            ))
+            .await?;
+            return Ok(());
+        }
+        Some(Some(duration)) => Some(duration),
+        None => None,
+    };
+
+    let expires_at = add(
+        &ctx.data().pool,
+        target,
+        id,
+        &reason,
+        &ctx.author().id,
+        expires_in,
+    )
+    .await?;
+    invalidate(ctx, target, id);
+
+    let expiry = match expires_at {
+        Some(expires_at) => format!("<t:{expires_at}:R>"),
+        None => "Never".into(),
+    };
+
+    let embed = CreateEmbed::default()
+        .title(format!("{} Blacklisted", target.name()))
+        .description(label)
+        .field("Reason", reason, false)
+        .field("Expires", expiry, true)
+        .color(Color::PURPLE);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+async fn remove_command(ctx: Context<'_>, target: Target, id: u64, label: String) -> CommandResult {
+    let removed = remove(&ctx.data().pool, target, id).await?;
+    invalidate(ctx, target, id);
+
+    if !removed {
+        ctx.send(error_reply(
+            &format!("{} not blacklisted", target.name()),
+            Some(&format!("{label} isn't blacklisted")),
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let embed = CreateEmbed::default()
+        .title(format!("{} Removed From Blacklist", target.name()))
+        .description(label)
+        .color(Color::PURPLE);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+async fn list_command(ctx: Context<'_>, target: Target) -> CommandResult {
+    let pool = &ctx.data().pool;
+
+    paginate(ctx, |page| async move {
+        let (entries, total) =
+            fetch_page(pool, target, PAGE_SIZE, i64::from(page) * PAGE_SIZE).await?;
+
+        let mut description = String::new();
+        for entry in &entries {
+            let name = match target {
+                Target::User => format!("<@{}>", entry.id),
+                Target::Guild => entry
+                    .id
+                    .to_string()
+                    .parse::<u64>()
+                    .ok()
+                    .and_then(|id| GuildId::new(id).name(ctx.cache()))
+                    .map(|name| format!("**{name}**"))
+                    .unwrap_or_else(|| "*Unknown guild*".into()),
+            };
+            description.push_str(&format!(
+                "{} `{}` - {}\n",
+                name,
+                entry.id,
+                entry.reason.as_deref().unwrap_or("*No reason*")
+            ));
+
+            let mut details = Vec::new();
+            if let Some(owner) = &entry.blacklisted_by {
+                details.push(format!("by <@{owner}>"));
+            }
+            if let Some(at) = entry.blacklisted_at {
+                details.push(format!("<t:{at}:R>"));
+            }
+            if let Some(expires_at) = entry.expires_at {
+                details.push(format!("expires <t:{expires_at}:R>"));
+            }
+            if !details.is_empty() {
+                description.push_str(&format!("-# {}\n", details.join(", ")));
+            }
+        }
+        if entries.is_empty() {
+            description.push_str("Nothing is blacklisted");
+        }
+
+        let embed = CreateEmbed::default()
+            .title(format!("Blacklisted {}s", target.name()))
+            .description(description)
+            .color(Color::PURPLE)
+            .footer(CreateEmbedFooter::new(format!(
+                "Page {}/{} - {} total",
+                page + 1,
+                page_count(total, PAGE_SIZE),
+                total
+            )));
+
+        Ok::<_, CommandError>(Page {
+            embed,
+            total_pages: page_count(total, PAGE_SIZE),
+        })
+    })
+    .await
+}
+
+/// Stop a user from using the bot
+#[poise::command(slash_command, rename = "add")]
+pub async fn blacklist_user_add(
+    ctx: Context<'_>,
+    #[description = "User to blacklist"] user: User,
+    #[description = "Why they're blacklisted"] reason: String,
+    #[description = "Lift the blacklist after this long, e.g. 7d. Never if empty"]
+    expires_in: Option<String>,
+) -> CommandResult {
+    let label = format!("{} (`{}`)", user.name, user.id);
+    add_command(ctx, Target::User, user.id.get(), label, reason, expires_in).await
+}
+
+/// Let a blacklisted user use the bot again
+#[poise::command(slash_command, rename = "remove")]
+pub async fn blacklist_user_remove(
+    ctx: Context<'_>,
+    #[description = "User to remove from the blacklist"] user: User,
+) -> CommandResult {
+    let label = format!("{} (`{}`)", user.name, user.id);
+    remove_command(ctx, Target::User, user.id.get(), label).await
+}
+
+/// List blacklisted users
+#[poise::command(slash_command, rename = "list")]
+pub async fn blacklist_user_list(ctx: Context<'_>) -> CommandResult {
+    list_command(ctx, Target::User).await
+}
+
+/// Stop a guild from using the bot
+#[poise::command(slash_command, rename = "add")]
+pub async fn blacklist_guild_add(
+    ctx: Context<'_>,
+    #[description = "ID of the guild to blacklist"] guild_id: String,
+    #[description = "Why it's blacklisted"] reason: String,
+    #[description = "Lift the blacklist after this long, e.g. 7d. Never if empty"]
+    expires_in: Option<String>,
+) -> CommandResult {
+    let Some(id) = parse_guild_id(ctx, &guild_id).await? else {
+        return Ok(());
+    };
+    add_command(
+        ctx,
+        Target::Guild,
+        id,
+        format!("`{id}`"),
+        reason,
+        expires_in,
+    )
+    .await
+}
+
+/// Let a blacklisted guild use the bot again
+#[poise::command(slash_command, rename = "remove")]
+pub async fn blacklist_guild_remove(
+    ctx: Context<'_>,
+    #[description = "ID of the guild to remove from the blacklist"] guild_id: String,
+) -> CommandResult {
+    let Some(id) = parse_guild_id(ctx, &guild_id).await? else {
+        return Ok(());
+    };
+    remove_command(ctx, Target::Guild, id, format!("`{id}`")).await
+}
+
+/// List blacklisted guilds
+#[poise::command(slash_command, rename = "list")]
+pub async fn blacklist_guild_list(ctx: Context<'_>) -> CommandResult {
+    list_command(ctx, Target::Guild).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(
+            parse_duration("12h"),
+            Some(Duration::from_secs(12 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration(" 7 d "),
+            Some(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            parse_duration("2w"),
+            Some(Duration::from_secs(14 * 24 * 60 * 60))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        assert_eq!(parse_duration("0d"), None);
+        assert_eq!(parse_duration("7"), None);
+        assert_eq!(parse_duration("d"), None);
+        assert_eq!(parse_duration("7y"), None);
+        assert_eq!(parse_duration("-7d"), None);
+        assert_eq!(parse_duration("99999999999999999999w"), None);
+    }
+
+    #[test]
+    fn caps_durations_at_ten_years() {
+        assert_eq!(parse_duration("3650d"), Some(MAX_EXPIRY));
+        assert_eq!(
+            parse_duration("521w"),
+            Some(Duration::from_secs(521 * 7 * 24 * 60 * 60))
+        );
+        assert_eq!(parse_duration("3651d"), None);
+        assert_eq!(parse_duration("522w"), None);
+        assert_eq!(parse_duration("5256001m"), None);
+    }
+}
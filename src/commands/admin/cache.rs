@@ -0,0 +1,45 @@
+use poise::{
+    serenity_prelude::{Color, CreateEmbed},
+    CreateReply,
+};
+
+use crate::{
+    commands::{strains::search_strains, utility::about},
+    types::{CommandResult, Context},
+};
+
+/// Clear the cached autocomplete lists and strain counts, e.g. after an import
+#[poise::command(
+    slash_command,
+    prefix_command,
+    rename = "clear-caches",
+    category = "Admin",
+    owners_only,
+    hide_in_help,
+    default_member_permissions = "ADMINISTRATOR"
+)]
+pub async fn clear_caches(ctx: Context<'_>) -> CommandResult {
+    search_strains::FLAVORS.invalidate().await;
+    search_strains::EFFECTS.invalidate().await;
+    search_strains::AILMENTS.invalidate().await;
+    about::STRAIN_COUNTS.invalidate().await;
+
+    let names = [
+        search_strains::FLAVORS.name(),
+        search_strains::EFFECTS.name(),
+        search_strains::AILMENTS.name(),
+        about::STRAIN_COUNTS.name(),
+    ];
+
+    let embed = CreateEmbed::default()
+        .title("Caches cleared")
+        .description(format!(
+            "Cleared {}. They'll be fetched again when next used",
+            names.join(", ")
+        ))
+        .color(Color::PURPLE);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,308 @@
+use poise::{
+    serenity_prelude::{Channel, Color, CreateEmbed, Permissions},
+    CreateReply,
+};
+use sqlx::{
+    types::{time::Time, BigDecimal},
+    PgPool,
+};
+
+use crate::{
+    commands::strains::puff::{insert_guild, DEFAULT_PUFF_COOLDOWN},
+    error_reply,
+    types::{CommandResult, Context},
+};
+
+/// Configure the bot for this server
+#[poise::command(
+    slash_command,
+    category = "Admin",
+    guild_only,
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD",
+    subcommands("config_daily_strain", "config_puff"),
+    subcommand_required
+)]
+pub async fn config(_ctx: Context<'_>) -> CommandResult {
+    // This command cannot be called by the client because of Discord's subcommand system.
+    // See https://discord.com/developers/docs/interactions/application-commands#subcommands-and-subcommand-groups
+    Ok(())
+}
+
+/// Parses a 24 hour `HH:MM` time
+fn parse_time(value: &str) -> Option<Time> {
+    let (hour, minute) = value.trim().split_once(':')?;
+    Time::from_hms(hour.parse().ok()?, minute.parse().ok()?, 0).ok()
+}
+
+async fn is_valid_timezone(pool: &PgPool, timezone: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM pg_timezone_names WHERE name = $1) AS "exists!";"#,
+        timezone
+    )
+    .fetch_one(pool)
+    .await
+}
+
+async fn autocomplete_timezones(ctx: Context<'_>, searching: &str) -> Vec<String> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT name AS "name!"
+        FROM pg_timezone_names
+        WHERE name ILIKE ('%' || $1 || '%')
+        ORDER BY name ASC
+        LIMIT 25;"#,
+        searching.trim()
+    )
+    .fetch_all(&ctx.data().pool)
+    .await
+    .unwrap_or_default()
+}
+
+/// Why the strain of the day can't be posted in `channel`, as an error title and message
+fn daily_strain_channel_error(
+    ctx: Context<'_>,
+    channel: &Channel,
+) -> Option<(&'static str, String)> {
+    let Channel::Guild(channel) = channel else {
+        return Some((
+            "Invalid Channel",
+            "Pick a channel in this server".to_string(),
+        ));
+    };
+
+    // Strain commands are all `nsfw_only`, and posts shouldn't get around that
+    if !channel.nsfw {
+        return Some((
+            "Channel Not Age-Restricted",
+            format!(
+                "The strain of the day can only be posted in age-restricted channels, and <#{}> isn't one",
+                channel.id
+            ),
+        ));
+    }
+
+    let needed = Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES | Permissions::EMBED_LINKS;
+    let bot_id = ctx.cache().current_user().id;
+    // Can't tell if the guild isn't cached, in which case failed posts are logged instead
+    let permissions = channel.permissions_for_user(ctx, bot_id).ok()?;
+    if permissions.contains(needed) {
+        return None;
+    }
+
+    let missing = needed
+        .difference(permissions)
+        .get_permission_names()
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<String>>()
+        .join(", ");
+    Some((
+        "Missing Permissions",
+        format!(
+            "BudBuddy needs {missing} in <#{}> to post there",
+            channel.id
+        ),
+    ))
+}
+
+/// Post the strain of the day to a channel every day
+///
+/// The timezone is also used for puff streaks and 4:20, even while daily posts are off.
+/// Run without any options to see the current settings
+#[poise::command(slash_command, rename = "daily-strain", guild_only)]
+pub async fn config_daily_strain(
+    ctx: Context<'_>,
+    #[description = "Age-restricted channel to post the strain of the day in"]
+    #[channel_types("Text", "News")]
+    channel: Option<Channel>,
+    #[description = "Local time to post at, as HH:MM (24 hour)"] time: Option<String>,
+    #[description = "Timezone for the time, puff streaks and 4:20, e.g. America/New_York"]
+    #[autocomplete = "autocomplete_timezones"]
+    timezone: Option<String>,
+    #[description = "Turn daily posts on or off"] enabled: Option<bool>,
+) -> CommandResult {
+    let pool = &ctx.data().pool;
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    let time = match time.as_deref().map(parse_time) {
+        Some(None) => {
+            ctx.send(error_reply(
+                "Invalid Time",
+                Some("Times must be in 24 hour `HH:MM` format, e.g. `16:20`"),
+            ))
+            .await?;
+            return Ok(());
+        }
+        Some(Some(time)) => Some(time),
+        None => None,
+    };
+
+    if let Some(timezone) = &timezone {
+        if !is_valid_timezone(pool, timezone).await? {
+            ctx.send(error_reply(
+                "Invalid Timezone",
+                Some(&format!(
+                    "`{timezone}` isn't a known timezone. Try one from the autocomplete list"
+                )),
+            ))
+            .await?;
+            return Ok(());
+        }
+    }
+
+    if enabled == Some(true) && channel.is_none() {
+        ctx.send(error_reply(
+            "Missing Channel",
+            Some("Pick a `channel` to post the strain of the day in"),
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    if let Some((title, message)) = channel
+        .as_ref()
+        .and_then(|channel| daily_strain_channel_error(ctx, channel))
+    {
+        ctx.send(error_reply(title, Some(&message))).await?;
+        return Ok(());
+    }
+
+    insert_guild(pool, &guild_id).await?;
+
+    let channel_id = channel.map(|c| BigDecimal::from(c.id().get()));
+    let settings = sqlx::query!(
+        r#"
+        UPDATE discord.guilds
+        SET
+            daily_strain_channel_id = CASE
+                WHEN $2 IS FALSE THEN NULL
+                ELSE COALESCE($3, daily_strain_channel_id)
+            END,
+            daily_strain_time = COALESCE($4, daily_strain_time),
+            daily_strain_timezone = COALESCE($5, daily_strain_timezone)
+        WHERE guild_id = $1
+        RETURNING
+            daily_strain_channel_id,
+            daily_strain_time,
+            daily_strain_timezone;"#,
+        BigDecimal::from(guild_id.get()),
+        enabled,
+        channel_id,
+        time,
+        timezone
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(settings) = settings else {
+        ctx.send(error_reply("Failed to save the settings", None))
+            .await?;
+        return Ok(());
+    };
+
+    let mut embed = CreateEmbed::default()
+        .title("Daily Strain")
+        .color(Color::PURPLE);
+
+    embed = match settings.daily_strain_channel_id {
+        Some(channel_id) => embed.description(format!(
+            "Posting the strain of the day in <#{}> at `{:02}:{:02}` ({})",
+            channel_id,
+            settings.daily_strain_time.hour(),
+            settings.daily_strain_time.minute(),
+            settings.daily_strain_timezone
+        )),
+        None => embed.description(format!(
+            "Daily posts are off. Pick a `channel` to turn them on\nServer timezone: {}",
+            settings.daily_strain_timezone
+        )),
+    };
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Set the `/puff take` cooldown, or turn `/puff` off in this server
+///
+/// Puff streaks and 4:20 follow the timezone set with `/config daily-strain`.
+/// Run without any options to see the current settings
+#[poise::command(slash_command, rename = "puff", guild_only)]
+pub async fn config_puff(
+    ctx: Context<'_>,
+    #[description = "Seconds each member has to wait between puffs, up to a day"]
+    #[max = 86_400]
+    cooldown: Option<u32>,
+    #[description = "Turn /puff on or off"] enabled: Option<bool>,
+) -> CommandResult {
+    let pool = &ctx.data().pool;
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+
+    insert_guild(pool, &guild_id).await?;
+
+    let settings = sqlx::query!(
+        r#"
+        UPDATE discord.guilds
+        SET
+            puffs_enabled = COALESCE($2, puffs_enabled),
+            puff_cooldown = COALESCE($3, puff_cooldown)
+        WHERE guild_id = $1
+        RETURNING puffs_enabled, puff_cooldown;"#,
+        BigDecimal::from(guild_id.get()),
+        enabled,
+        cooldown.map(|c| c as i32)
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    let Some(settings) = settings else {
+        ctx.send(error_reply("Failed to save the settings", None))
+            .await?;
+        return Ok(());
+    };
+
+    let description = if settings.puffs_enabled {
+        format!(
+            "Members can take a puff every `{}` seconds (default `{}`)",
+            settings.puff_cooldown,
+            DEFAULT_PUFF_COOLDOWN.as_secs()
+        )
+    } else {
+        "`/puff` is off in this server. Set `enabled` to turn it back on".into()
+    };
+
+    let embed = CreateEmbed::default()
+        .title("Puffs")
+        .description(description)
+        .color(Color::PURPLE);
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_times() {
+        assert_eq!(parse_time("16:20"), Time::from_hms(16, 20, 0).ok());
+        assert_eq!(parse_time(" 4:05 "), Time::from_hms(4, 5, 0).ok());
+        assert_eq!(parse_time("00:00"), Some(Time::MIDNIGHT));
+    }
+
+    #[test]
+    fn rejects_invalid_times() {
+        assert_eq!(parse_time("24:00"), None);
+        assert_eq!(parse_time("12:60"), None);
+        assert_eq!(parse_time("16"), None);
+        assert_eq!(parse_time("4:20pm"), None);
+        assert_eq!(parse_time(""), None);
+    }
+}
@@ -1 +1,4 @@
+pub mod blacklist;
+pub mod cache;
+pub mod config;
 pub mod register;
@@ -0,0 +1,126 @@
+use poise::{
+    serenity_prelude::{Color, CreateEmbed},
+    CreateReply,
+};
+
+use super::fetch_strain::{fetch_strain, resolve_strain, StrainDetails};
+use crate::{
+    error_reply,
+    types::{CommandError, CommandResult, Context},
+};
+
+/// Zero width space, used to pad inline fields into two columns
+const SPACER: &str = "\u{200b}";
+
+fn list_or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        return "*None*".into();
+    }
+    values.join(", ")
+}
+
+/// Values in `a` that are (or aren't) in `b`, ignoring case
+fn partition(a: &[String], b: &[String]) -> (Vec<String>, Vec<String>) {
+    a.iter()
+        .cloned()
+        .partition(|value| b.iter().any(|other| other.eq_ignore_ascii_case(value)))
+}
+
+fn compare_embed(a: StrainDetails, b: StrainDetails) -> CreateEmbed {
+    let subspecies = |s: &StrainDetails| match s.subspecies {
+        Some(subspecies) => subspecies.to_string(),
+        None => "Unknown".into(),
+    };
+
+    let rows = [
+        ("🎨 Subspecies", subspecies(&a), subspecies(&b)),
+        (
+            "🔺 Positive Effects",
+            list_or_none(&a.positive_effects),
+            list_or_none(&b.positive_effects),
+        ),
+        (
+            "🔻 Negative Effects",
+            list_or_none(&a.negative_effects),
+            list_or_none(&b.negative_effects),
+        ),
+        (
+            "👅 Flavors",
+            list_or_none(&a.flavors),
+            list_or_none(&b.flavors),
+        ),
+        (
+            "💊 Ailments",
+            list_or_none(&a.ailments),
+            list_or_none(&b.ailments),
+        ),
+    ];
+
+    let mut embed = CreateEmbed::default()
+        .title(format!("{} vs {}", a.name, b.name))
+        .color(Color::PURPLE);
+
+    for (category, value_a, value_b) in rows {
+        embed = embed
+            .field(format!("{category} - {}", a.name), value_a, true)
+            .field(format!("{category} - {}", b.name), value_b, true)
+            .field(SPACER, SPACER, true);
+    }
+
+    let effects_a = [a.positive_effects.as_slice(), a.negative_effects.as_slice()].concat();
+    let effects_b = [b.positive_effects.as_slice(), b.negative_effects.as_slice()].concat();
+
+    let (shared, only_a) = partition(&effects_a, &effects_b);
+    let (_, only_b) = partition(&effects_b, &effects_a);
+
+    embed
+        .field("🤝 Shared Effects", list_or_none(&shared), false)
+        .field(format!("Only {}", a.name), list_or_none(&only_a), true)
+        .field(format!("Only {}", b.name), list_or_none(&only_b), true)
+}
+
+/// Fetches the best match for `query`, replying with an error if there isn't one
+async fn find_strain(ctx: Context<'_>, query: &str) -> Result<Option<StrainDetails>, CommandError> {
+    let pool = &ctx.data().pool;
+    let strain = match resolve_strain(pool, query).await? {
+        Some(id) => fetch_strain(pool, id).await?,
+        None => None,
+    };
+
+    if strain.is_none() {
+        ctx.send(error_reply(
+            "Strain Not Found",
+            Some(&format!("Couldn't find a strain matching: `{query}`")),
+        ))
+        .await?;
+    }
+    Ok(strain)
+}
+
+/// Compares two strains side by side
+#[poise::command(
+    slash_command,
+    required_bot_permissions = "SEND_MESSAGES",
+    nsfw_only = true,
+    category = "Strains"
+)]
+pub async fn compare(
+    ctx: Context<'_>,
+    #[description = "Name or ID of the first strain"]
+    #[autocomplete = "super::search_strains::autocomplete_strain_names"]
+    a: String,
+    #[description = "Name or ID of the second strain"]
+    #[autocomplete = "super::search_strains::autocomplete_strain_names"]
+    b: String,
+) -> CommandResult {
+    let Some(a) = find_strain(ctx, &a).await? else {
+        return Ok(());
+    };
+    let Some(b) = find_strain(ctx, &b).await? else {
+        return Ok(());
+    };
+
+    ctx.send(CreateReply::default().embed(compare_embed(a, b)))
+        .await?;
+    Ok(())
+}
@@ -1,28 +1,40 @@
-use super::search_strains::Subspecies;
+use std::time::Duration;
+
+use super::{
+    search_strains::{did_you_mean, Subspecies},
+    similar_strains::{similar_embed, similar_strains, SIMILAR_LIMIT},
+};
 use crate::{
     error_reply,
-    types::{CommandResult, Context},
+    types::{CommandError, CommandResult, Context},
 };
 use poise::{
-    serenity_prelude::{Color, CreateEmbed, CreateEmbedFooter},
-    CreateReply,
+    serenity_prelude::{
+        self as serenity, ButtonStyle, Color, ComponentInteractionDataKind, CreateActionRow,
+        CreateButton, CreateEmbed, CreateEmbedFooter, CreateInteractionResponseMessage,
+        CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, ReactionType,
+    },
+    CreateReply, ReplyHandle,
 };
-use tracing::warn;
+use sqlx::PgPool;
 
-/// Fetches a strain by it's ID
-#[poise::command(
-    slash_command,
-    required_bot_permissions = "SEND_MESSAGES",
-    nsfw_only = true,
-    category = "Strains"
-)]
-pub async fn strain(
-    ctx: Context<'_>,
-    #[description = "ID of the strain"] id: i64,
-) -> CommandResult {
-    let pool = &ctx.data().pool;
+/// Everything shown on a strain's embed
+#[derive(Debug)]
+pub struct StrainDetails {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub subspecies: Option<Subspecies>,
+    pub image_url: Option<String>,
+    pub positive_effects: Vec<String>,
+    pub negative_effects: Vec<String>,
+    pub flavors: Vec<String>,
+    pub ailments: Vec<String>,
+}
 
-    let result = sqlx::query!(
+/// Fetches a strain along with its effects, flavors and ailments
+pub async fn fetch_strain(pool: &PgPool, id: i64) -> Result<Option<StrainDetails>, sqlx::Error> {
+    let strain = sqlx::query!(
         r#"
         SELECT
             s.name,
@@ -68,27 +80,23 @@ pub async fn strain(
         "#,
         id
     )
-    .fetch_one(pool)
-    .await;
-
-    let strain = match result {
-        Ok(s) => s,
-        Err(e) => match e {
-            sqlx::Error::RowNotFound => {
-                ctx.send(error_reply(
-                    "Strain Not Found",
-                    Some(&format!("Couldn't find strain with id: `{}`", id)),
-                ))
-                .await?;
-                return Ok(());
-            }
-            other => {
-                warn!("Error: {:#?}", other);
-                return Err(other)?;
-            }
-        },
-    };
+    .fetch_optional(pool)
+    .await?;
 
+    Ok(strain.map(|s| StrainDetails {
+        id,
+        name: s.name,
+        description: s.description,
+        subspecies: s.subspecies,
+        image_url: s.image_url,
+        positive_effects: s.positive_effects.unwrap_or_default(),
+        negative_effects: s.negative_effects.unwrap_or_default(),
+        flavors: s.flavors.unwrap_or_default(),
+        ailments: s.ailments.unwrap_or_default(),
+    }))
+}
+
+pub fn strain_embed(strain: StrainDetails) -> CreateEmbed {
     let mut embed = CreateEmbed::default()
         .title(strain.name)
         .description(
@@ -97,49 +105,333 @@ pub async fn strain(
                 .unwrap_or("No description available".into()),
         )
         .color(Color::PURPLE)
-        .footer(CreateEmbedFooter::new(format!("ID: {}", id)));
+        .footer(CreateEmbedFooter::new(format!("ID: {}", strain.id)));
 
     if let Some(s) = strain.subspecies {
         embed = embed.field("🎨 Subspecies", s.to_string(), false);
     }
 
-    if let Some(s) = strain.positive_effects.as_ref() {
-        if !s.is_empty() {
-            let effects = s.join(", ");
+    if !strain.positive_effects.is_empty() {
+        let effects = strain.positive_effects.join(", ");
 
-            embed = embed.field("🔺 Positive Effects", effects, false);
-        }
+        embed = embed.field("🔺 Positive Effects", effects, false);
     }
 
-    if let Some(s) = strain.negative_effects.as_ref() {
-        if !s.is_empty() {
-            let effects = s.join(", ");
+    if !strain.negative_effects.is_empty() {
+        let effects = strain.negative_effects.join(", ");
 
-            embed = embed.field("🔻 Negative Effects", effects, true);
-        }
+        embed = embed.field("🔻 Negative Effects", effects, true);
     }
 
-    if let Some(s) = strain.flavors.as_ref() {
-        if !s.is_empty() {
-            let effects = s.join(", ");
+    if !strain.flavors.is_empty() {
+        let flavors = strain.flavors.join(", ");
 
-            embed = embed.field("👅 Flavors", effects, false);
-        }
+        embed = embed.field("👅 Flavors", flavors, false);
     }
 
-    if let Some(s) = strain.ailments.as_ref() {
-        if !s.is_empty() {
-            let effects = s.join(", ");
+    if !strain.ailments.is_empty() {
+        let ailments = strain.ailments.join(", ");
 
-            embed = embed.field("💊 Ailments", effects, false);
-        }
+        embed = embed.field("💊 Ailments", ailments, false);
     }
 
     if let Some(image) = strain.image_url {
         embed = embed.image(image);
     }
 
-    let reply = CreateReply::default().embed(embed);
-    ctx.send(reply).await?;
+    embed
+}
+
+/// Strain candidates for a name, used when a name is ambiguous
+struct StrainMatch {
+    id: i64,
+    name: String,
+    subspecies: Option<Subspecies>,
+}
+
+/// Strains named exactly `name` (ignoring case), or the closest matches if there are none
+async fn strains_by_name(pool: &PgPool, name: &str) -> Result<Vec<StrainMatch>, sqlx::Error> {
+    let exact = sqlx::query_as!(
+        StrainMatch,
+        r#"
+        SELECT
+            id,
+            name,
+            subspecies AS "subspecies:Subspecies"
+        FROM public.strains
+        WHERE LOWER(name) = LOWER($1)
+        ORDER BY id ASC
+        LIMIT 25;"#,
+        name
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if !exact.is_empty() {
+        return Ok(exact);
+    }
+
+    sqlx::query_as!(
+        StrainMatch,
+        r#"
+        SELECT
+            id,
+            name,
+            subspecies AS "subspecies:Subspecies"
+        FROM public.strains
+        WHERE name ILIKE ('%' || $1 || '%') OR name % $1
+        ORDER BY SIMILARITY(name, $1) DESC, id ASC
+        LIMIT 25;"#,
+        name
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Look up strains
+#[poise::command(
+    slash_command,
+    required_bot_permissions = "SEND_MESSAGES",
+    nsfw_only = true,
+    category = "Strains",
+    subcommands(
+        "strain_info",
+        "super::random_strain::strain_random",
+        "super::random_strain::strain_daily",
+        "super::similar_strains::strain_similar"
+    ),
+    subcommand_required
+)]
+pub async fn strain(_ctx: Context<'_>) -> CommandResult {
+    // This command cannot be called by the client because of Discord's subcommand system.
+    // See https://discord.com/developers/docs/interactions/application-commands#subcommands-and-subcommand-groups
     Ok(())
 }
+
+/// Best match for a strain name or ID, without asking the user to pick one
+pub async fn resolve_strain(pool: &PgPool, query: &str) -> Result<Option<i64>, sqlx::Error> {
+    if let Ok(id) = query.trim().parse::<i64>() {
+        let exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM public.strains WHERE id = $1) AS "exists!";"#,
+            id
+        )
+        .fetch_one(pool)
+        .await?;
+        if exists {
+            return Ok(Some(id));
+        }
+    }
+
+    Ok(strains_by_name(pool, query.trim())
+        .await?
+        .first()
+        .map(|strain| strain.id))
+}
+
+/// Fetches a strain by its name or ID
+#[poise::command(
+    slash_command,
+    rename = "info",
+    required_bot_permissions = "SEND_MESSAGES",
+    nsfw_only = true
+)]
+pub async fn strain_info(
+    ctx: Context<'_>,
+    #[description = "Name of the strain, leave empty when using id"]
+    #[autocomplete = "super::search_strains::autocomplete_strain_names"]
+    name: Option<String>,
+    #[description = "ID of the strain, leave empty when using name"] id: Option<i64>,
+) -> CommandResult {
+    let pool = &ctx.data().pool;
+
+    let (id, handle) = match (id, name) {
+        (Some(_), Some(_)) => {
+            ctx.send(error_reply(
+                "Conflicting Options",
+                Some("Provide either the `name` or `id` of a strain, not both"),
+            ))
+            .await?;
+            return Ok(());
+        }
+        (Some(id), None) => (id, None),
+        (None, Some(name)) => match select_strain(ctx, &name).await? {
+            Some(selected) => selected,
+            None => return Ok(()),
+        },
+        (None, None) => {
+            ctx.send(error_reply(
+                "Missing Strain",
+                Some("Provide either the `name` or `id` of a strain"),
+            ))
+            .await?;
+            return Ok(());
+        }
+    };
+
+    let reply = match fetch_strain(pool, id).await? {
+        Some(strain) => {
+            let name = strain.name.clone();
+            let embed = strain_embed(strain);
+            return show_strain(ctx, handle, id, name, embed).await;
+        }
+        None => error_reply(
+            "Strain Not Found",
+            Some(&format!("Couldn't find strain with id: `{}`", id)),
+        ),
+    };
+
+    match handle {
+        Some(handle) => handle.edit(ctx, reply.components(vec![])).await?,
+        None => {
+            ctx.send(reply).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Sends (or edits `handle` to show) a strain, with a button to list similar strains
+async fn show_strain(
+    ctx: Context<'_>,
+    handle: Option<ReplyHandle<'_>>,
+    id: i64,
+    name: String,
+    embed: CreateEmbed,
+) -> CommandResult {
+    let similar_id = format!("{}-strain-similar", ctx.id());
+    let components = vec![CreateActionRow::Buttons(vec![CreateButton::new(
+        &similar_id,
+    )
+    .style(ButtonStyle::Secondary)
+    .label("Similar")
+    .emoji(ReactionType::Unicode("🔍".into()))])];
+
+    let reply = CreateReply::default()
+        .embed(embed.clone())
+        .components(components);
+    let handle = match handle {
+        Some(handle) => {
+            handle.edit(ctx, reply).await?;
+            handle
+        }
+        None => ctx.send(reply).await?,
+    };
+
+    let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .custom_ids(vec![similar_id])
+        .timeout(Duration::from_secs(120))
+        .await
+    else {
+        handle
+            .edit(ctx, CreateReply::default().embed(embed).components(vec![]))
+            .await?;
+        return Ok(());
+    };
+
+    let similar = similar_strains(&ctx.data().pool, id, SIMILAR_LIMIT).await?;
+    interaction
+        .create_response(
+            ctx,
+            serenity::CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::default()
+                    .embeds(vec![embed, similar_embed(&name, &similar)])
+                    .components(vec![]),
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+/// Resolves a name to a strain ID, asking the user to pick one if the name is ambiguous.
+///
+/// If a menu was shown, its reply handle is returned so it can be edited with the strain.
+/// Returns `None` if the reply was already handled (not found, or timed out)
+async fn select_strain<'a>(
+    ctx: Context<'a>,
+    name: &str,
+) -> Result<Option<(i64, Option<ReplyHandle<'a>>)>, CommandError> {
+    let pool = &ctx.data().pool;
+    let matches = strains_by_name(pool, name).await?;
+
+    match matches.as_slice() {
+        [] => {
+            let mut message = format!("Couldn't find a strain named: `{name}`");
+            if let Some(suggestion) = did_you_mean(pool, name).await? {
+                message.push_str(&format!("\n\nDid you mean **{suggestion}**?"));
+            }
+            ctx.send(error_reply("Strain Not Found", Some(&message)))
+                .await?;
+            return Ok(None);
+        }
+        [strain] => return Ok(Some((strain.id, None))),
+        _ => {}
+    }
+
+    let select_id = format!("{}-strain-select", ctx.id());
+    let options = matches
+        .iter()
+        .map(|strain| {
+            let description = match strain.subspecies {
+                Some(subspecies) => format!("ID: {} - {}", strain.id, subspecies),
+                None => format!("ID: {}", strain.id),
+            };
+            CreateSelectMenuOption::new(&strain.name, strain.id.to_string())
+                .description(description)
+        })
+        .collect();
+
+    let embed = CreateEmbed::default()
+        .title(format!("Multiple strains match \"{name}\""))
+        .description("Pick one from the menu below")
+        .color(Color::PURPLE);
+
+    let reply = CreateReply::default()
+        .embed(embed)
+        .components(vec![CreateActionRow::SelectMenu(
+            CreateSelectMenu::new(&select_id, CreateSelectMenuKind::String { options })
+                .placeholder("Select a strain"),
+        )]);
+    let handle = ctx.send(reply).await?;
+
+    let Some(interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .custom_ids(vec![select_id])
+        .timeout(Duration::from_secs(60))
+        .await
+    else {
+        handle
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .embed(CreateEmbed::default().title("Timed out"))
+                    .components(vec![]),
+            )
+            .await?;
+        return Ok(None);
+    };
+
+    // The message is edited with the strain afterwards
+    interaction
+        .create_response(ctx, serenity::CreateInteractionResponse::Acknowledge)
+        .await?;
+
+    let selected = match &interaction.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => {
+            values.first().and_then(|v| v.parse::<i64>().ok())
+        }
+        _ => None,
+    };
+
+    match selected {
+        Some(id) => Ok(Some((id, Some(handle)))),
+        None => {
+            handle
+                .edit(
+                    ctx,
+                    error_reply("Strain Not Found", None).components(vec![]),
+                )
+                .await?;
+            Ok(None)
+        }
+    }
+}
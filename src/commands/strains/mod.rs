@@ -1,3 +1,10 @@
+pub mod compare_strains;
 pub mod fetch_strain;
 pub mod puff;
+pub mod puff_achievements;
+pub mod puff_history;
+pub mod puff_leaderboard;
+pub mod puff_reset;
+pub mod random_strain;
 pub mod search_strains;
+pub mod similar_strains;
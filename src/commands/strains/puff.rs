@@ -1,23 +1,40 @@
 use std::time::Duration;
 
 use poise::{
-    serenity_prelude::{
-        self as serenity, Color, CreateActionRow, CreateButton, CreateEmbed,
-        CreateInteractionResponseMessage, GuildId, ReactionType, UserId,
+    serenity_prelude::{Color, CreateEmbed, CreateEmbedFooter, GuildId, User, UserId},
+    CooldownConfig, CreateReply,
+};
+use sqlx::{
+    types::{
+        time::{Date, PrimitiveDateTime},
+        BigDecimal,
     },
-    CreateReply,
+    PgExecutor,
 };
-use sqlx::{types::BigDecimal, PgPool};
 
-use crate::types::{CommandError, CommandResult, Context};
+use super::puff_achievements::award_achievements;
+use crate::{
+    error_reply,
+    types::{CheckError, CommandError, CommandResult, Context},
+    user_data,
+};
 use anyhow::anyhow;
 
 /// Track puffs on the leaderboard
 #[poise::command(
     slash_command,
     category = "Strains",
-    subcommands("puff_take", "puff_reset"),
-    subcommand_required
+    subcommands(
+        "puff_take",
+        "puff_stats",
+        "super::puff_reset::puff_reset",
+        "super::puff_reset::puff_undo_reset",
+        "super::puff_leaderboard::puff_leaderboard",
+        "super::puff_history::puff_history",
+        "super::puff_achievements::puff_achievements"
+    ),
+    subcommand_required,
+    check = "puffs_enabled_check"
 )]
 pub async fn puff(_ctx: Context<'_>) -> CommandResult {
     // This command cannot be called by the client because of Discord's subcommand system.
@@ -25,161 +42,390 @@ pub async fn puff(_ctx: Context<'_>) -> CommandResult {
     Ok(())
 }
 
+/// Cooldown for `/puff take` in DMs and guilds that haven't configured one
+pub const DEFAULT_PUFF_COOLDOWN: Duration = Duration::from_secs(30);
+
+pub struct PuffSettings {
+    pub enabled: bool,
+    pub cooldown: Duration,
+}
+
+impl Default for PuffSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cooldown: DEFAULT_PUFF_COOLDOWN,
+        }
+    }
+}
+
+/// The guild's `/config puff` settings, or the defaults in DMs and unconfigured guilds
+pub async fn puff_settings(
+    executor: impl PgExecutor<'_>,
+    guild_id: Option<&GuildId>,
+) -> Result<PuffSettings, sqlx::Error> {
+    let Some(guild_id) = guild_id else {
+        return Ok(PuffSettings::default());
+    };
+
+    let settings = sqlx::query!(
+        "SELECT puffs_enabled, puff_cooldown FROM discord.guilds WHERE guild_id = $1;",
+        BigDecimal::from(guild_id.get())
+    )
+    .fetch_optional(executor)
+    .await?
+    .map(|r| PuffSettings {
+        enabled: r.puffs_enabled,
+        cooldown: Duration::from_secs(r.puff_cooldown.max(0) as u64),
+    })
+    .unwrap_or_default();
+
+    Ok(settings)
+}
+
+/// Stops every `/puff` subcommand in guilds that turned puffs off
+async fn puffs_enabled_check(ctx: Context<'_>) -> Result<bool, CommandError> {
+    let settings = puff_settings(&ctx.data().pool, ctx.guild_id().as_ref()).await?;
+    if !settings.enabled {
+        return Err(CheckError::Disabled.into());
+    }
+    Ok(true)
+}
+
+/// Per member cooldown for `/puff take`, using the guild's configured length.
+///
+/// Poise still starts the command's cooldown tracker after every use, only the length is dynamic
+async fn puff_cooldown_check(ctx: Context<'_>) -> Result<bool, CommandError> {
+    let settings = puff_settings(&ctx.data().pool, ctx.guild_id().as_ref()).await?;
+
+    // The member bucket only exists in guilds, so DMs fall back to the user bucket
+    let config = match ctx.guild_id() {
+        Some(_) => CooldownConfig {
+            member: Some(settings.cooldown),
+            ..Default::default()
+        },
+        None => CooldownConfig {
+            user: Some(settings.cooldown),
+            ..Default::default()
+        },
+    };
+
+    let remaining = ctx
+        .command()
+        .cooldowns
+        .lock()
+        .unwrap()
+        .remaining_cooldown(ctx.cooldown_context(), &config);
+
+    match remaining {
+        Some(remaining) => Err(CheckError::Cooldown(remaining).into()),
+        None => Ok(true),
+    }
+}
+
 /// Take a puff, incrementing your total puffs by one
 ///
-/// If used in a guild, it will also increment guild puffs by one
-#[poise::command(slash_command, rename = "take", member_cooldown = 30)]
+/// If used in a guild, it will also increment guild puffs and your puffs in that guild by one
+#[poise::command(slash_command, rename = "take", check = "puff_cooldown_check")]
 pub async fn puff_take(ctx: Context<'_>) -> CommandResult {
     let user_id = ctx.author().id;
 
     let pool = &ctx.data().pool;
+    if user_data::is_opted_out(pool, &user_id).await? {
+        ctx.send(error_reply(
+            "You've opted out",
+            Some(
+                "Puffs aren't tracked for you. Use `/privacy opt-in` to start tracking them again",
+            ),
+        ))
+        .await?;
+        return Ok(());
+    }
 
-    insert_user(pool, &user_id).await;
-    let user_puffs = increment_user_puffs(pool, &user_id).await?;
+    // All counters are updated together, or not at all
+    let mut tx = pool.begin().await?;
+
+    insert_user(&mut *tx, &user_id).await?;
+    let user_puffs = increment_user_puffs(&mut *tx, &user_id).await?;
+    let now = local_now(&mut *tx, ctx.guild_id().as_ref()).await?;
+    let streak = update_streak(&mut *tx, &user_id, now.date()).await?;
     let mut embed = CreateEmbed::new()
         .title(format!("Total puffs - {}", user_puffs))
         .color(Color::PURPLE);
 
+    if streak > 1 {
+        embed = embed.footer(CreateEmbedFooter::new(format!("🔥 {streak} day streak")));
+    }
+
     if let Some(guild_id) = ctx.guild_id() {
-        insert_guild(pool, &guild_id).await;
-        let guild_puffs = increment_guild_puffs(pool, &guild_id).await?;
-        embed = embed.description(format!("Total server puffs - {}", guild_puffs));
+        insert_guild(&mut *tx, &guild_id).await?;
+        let guild_puffs = increment_guild_puffs(&mut *tx, &guild_id).await?;
+        let member_puffs = increment_member_puffs(&mut *tx, &guild_id, &user_id).await?;
+        embed = embed.description(format!(
+            "Total server puffs - {}\nYour server puffs - {}",
+            guild_puffs, member_puffs
+        ));
+    }
+
+    record_puff(&mut *tx, &user_id, ctx.guild_id().as_ref()).await?;
+
+    for achievement in
+        award_achievements(&mut *tx, &user_id, user_puffs, streak, now.time()).await?
+    {
+        embed = embed.field(
+            format!("Achievement unlocked: {}", achievement.name()),
+            achievement.description(),
+            false,
+        );
     }
 
+    tx.commit().await?;
+
     let reply = CreateReply::default().embed(embed);
 
     ctx.send(reply).await?;
     Ok(())
 }
 
-async fn insert_user(pool: &PgPool, user_id: &UserId) -> bool {
-    let result = sqlx::query!(
-        "INSERT INTO discord.users (user_id) VALUES ($1) ON CONFLICT DO NOTHING;",
+/// Never creates a row for users who ran `/privacy opt-out`
+pub async fn insert_user(
+    executor: impl PgExecutor<'_>,
+    user_id: &UserId,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO discord.users (user_id)
+        SELECT $1
+        WHERE NOT EXISTS (SELECT 1 FROM discord.opted_out_users WHERE user_id = $1)
+        ON CONFLICT DO NOTHING;"#,
         BigDecimal::from(user_id.get())
     )
-    .execute(pool)
-    .await;
-
-    result.is_ok()
+    .execute(executor)
+    .await?;
+    Ok(())
 }
 
-async fn increment_user_puffs(pool: &PgPool, user_id: &UserId) -> Result<i64, CommandError> {
+async fn increment_user_puffs(
+    executor: impl PgExecutor<'_>,
+    user_id: &UserId,
+) -> Result<i64, CommandError> {
     sqlx::query_scalar!(
         r#"
         UPDATE discord.users
         SET puffs= puffs + 1
         WHERE
             user_id = $1
-            AND NOT is_blacklisted
+            AND NOT (is_blacklisted AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()))
         RETURNING puffs;"#,
         BigDecimal::from(user_id.get())
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .map_err(|_| anyhow!("Failed to update user puffs"))
 }
 
-async fn increment_guild_puffs(pool: &PgPool, guild_id: &GuildId) -> Result<i64, CommandError> {
+/// The current time in the guild's `/config daily-strain` timezone, or UTC in DMs
+async fn local_now(
+    executor: impl PgExecutor<'_>,
+    guild_id: Option<&GuildId>,
+) -> Result<PrimitiveDateTime, CommandError> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT NOW() AT TIME ZONE COALESCE(
+            (SELECT daily_strain_timezone FROM discord.guilds WHERE guild_id = $1),
+            'UTC'
+        ) AS "now!";"#,
+        guild_id.map(|id| BigDecimal::from(id.get()))
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|_| anyhow!("Failed to get the local time"))
+}
+
+/// Extends the user's daily streak for a puff on the local date `today`, returning its new length.
+///
+/// Puffs in guilds further behind in time than the last one don't break the streak
+async fn update_streak(
+    executor: impl PgExecutor<'_>,
+    user_id: &UserId,
+    today: Date,
+) -> Result<i32, CommandError> {
+    sqlx::query_scalar!(
+        r#"
+        WITH streak AS (
+            SELECT
+                CASE
+                    WHEN last_puff_date >= $2 THEN current_streak
+                    WHEN last_puff_date = $2::DATE - 1 THEN current_streak + 1
+                    ELSE 1
+                END AS current_streak
+            FROM discord.users
+            WHERE user_id = $1
+        )
+        UPDATE discord.users u
+        SET
+            current_streak = streak.current_streak,
+            longest_streak = GREATEST(u.longest_streak, streak.current_streak),
+            last_puff_date = GREATEST(u.last_puff_date, $2)
+        FROM streak
+        WHERE u.user_id = $1
+        RETURNING u.current_streak;"#,
+        BigDecimal::from(user_id.get()),
+        today
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|_| anyhow!("Failed to update puff streak"))
+}
+
+async fn increment_guild_puffs(
+    executor: impl PgExecutor<'_>,
+    guild_id: &GuildId,
+) -> Result<i64, CommandError> {
     sqlx::query_scalar!(
         r#"
         UPDATE discord.guilds
         SET puffs = puffs + 1
         WHERE
             guild_id = $1
-            AND NOT is_blacklisted
+            AND NOT (is_blacklisted AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()))
         RETURNING puffs;"#,
         BigDecimal::from(guild_id.get())
     )
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await
     .map_err(|_| anyhow!("Failed to update guild puffs"))
 }
 
-async fn insert_guild(pool: &PgPool, guild_id: &GuildId) -> bool {
-    let result = sqlx::query!(
-        "INSERT INTO discord.guilds (guild_id) VALUES ($1) ON CONFLICT DO NOTHING;",
-        BigDecimal::from(guild_id.get())
+async fn increment_member_puffs(
+    executor: impl PgExecutor<'_>,
+    guild_id: &GuildId,
+    user_id: &UserId,
+) -> Result<i64, CommandError> {
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO discord.guild_members (guild_id, user_id, puffs)
+        VALUES ($1, $2, 1)
+        ON CONFLICT (guild_id, user_id) DO UPDATE
+        SET puffs = guild_members.puffs + 1
+        RETURNING puffs;"#,
+        BigDecimal::from(guild_id.get()),
+        BigDecimal::from(user_id.get())
     )
-    .execute(pool)
-    .await;
-
-    result.is_ok()
+    .fetch_one(executor)
+    .await
+    .map_err(|_| anyhow!("Failed to update member puffs"))
 }
 
-/// Reset your puff count to 0!
-#[poise::command(slash_command, rename = "reset", user_cooldown = 30)]
-pub async fn puff_reset(ctx: Context<'_>) -> CommandResult {
-    let initial_embed = CreateEmbed::default().title("Are you sure?").description(
-        "Resetting your puff count is an irreversable action. This will set your puff count to 0",
-    );
+async fn record_puff(
+    executor: impl PgExecutor<'_>,
+    user_id: &UserId,
+    guild_id: Option<&GuildId>,
+) -> Result<(), CommandError> {
+    sqlx::query!(
+        "INSERT INTO discord.puff_events (user_id, guild_id) VALUES ($1, $2);",
+        BigDecimal::from(user_id.get()),
+        guild_id.map(|id| BigDecimal::from(id.get()))
+    )
+    .execute(executor)
+    .await
+    .map_err(|_| anyhow!("Failed to record puff"))?;
+    Ok(())
+}
 
-    let cancel_id = format!("{}-puff-reset-cancel", ctx.id());
-    let confirm_id = format!("{}-puff-reset-confirm", ctx.id());
+pub async fn insert_guild(
+    executor: impl PgExecutor<'_>,
+    guild_id: &GuildId,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO discord.guilds (guild_id) VALUES ($1) ON CONFLICT DO NOTHING;",
+        BigDecimal::from(guild_id.get())
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
 
-    let buttons = vec![
-        CreateButton::new(&cancel_id)
-            .style(poise::serenity_prelude::ButtonStyle::Primary)
-            .label("Cancel")
-            .emoji(ReactionType::Unicode("✖️".into())),
-        CreateButton::new(&confirm_id)
-            .label("Reset")
-            .style(poise::serenity_prelude::ButtonStyle::Danger)
-            .emoji(ReactionType::Unicode("⚠️".into())),
-    ];
+/// Show your puff totals
+///
+/// If used in a guild, it also shows your puffs in that guild and the guild's total
+#[poise::command(slash_command, rename = "stats", user_cooldown = 5)]
+pub async fn puff_stats(
+    ctx: Context<'_>,
+    #[description = "User to show stats for, defaults to you"] user: Option<User>,
+) -> CommandResult {
+    let user = user.as_ref().unwrap_or(ctx.author());
+    let guild_id = ctx.guild_id().map(|id| BigDecimal::from(id.get()));
 
-    let reply = CreateReply::default()
-        .embed(initial_embed)
-        .components(vec![CreateActionRow::Buttons(buttons)]);
+    let stats = sqlx::query!(
+        r#"
+        SELECT
+            u.puffs AS "user_puffs?",
+            -- A streak is broken once a whole (local) day passes without a puff
+            CASE
+                WHEN u.last_puff_date >= (
+                    NOW() AT TIME ZONE COALESCE(
+                        (SELECT daily_strain_timezone FROM discord.guilds WHERE guild_id = $2),
+                        'UTC'
+                    )
+                )::DATE - 1 THEN u.current_streak
+                ELSE 0
+            END AS current_streak,
+            u.longest_streak AS "longest_streak?",
+            (
+                SELECT puffs
+                FROM discord.guild_members
+                WHERE guild_id = $2 AND user_id = $1
+            ) AS member_puffs,
+            (
+                SELECT puffs
+                FROM discord.guilds
+                WHERE
+                    guild_id = $2
+                    AND NOT (is_blacklisted AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()))
+            ) AS guild_puffs
+        FROM (SELECT 1) AS _
+        LEFT JOIN discord.users u
+            ON u.user_id = $1
+            AND NOT (u.is_blacklisted AND (u.blacklist_expires_at IS NULL OR u.blacklist_expires_at > NOW()));"#,
+        BigDecimal::from(user.id.get()),
+        guild_id
+    )
+    .fetch_one(&ctx.data().pool)
+    .await?;
 
-    ctx.send(reply).await?;
+    let mut embed = CreateEmbed::new()
+        .title(format!("{}'s puffs", user.name))
+        .color(Color::PURPLE)
+        .field(
+            "Total puffs",
+            format!("`{}`", stats.user_puffs.unwrap_or(0)),
+            true,
+        )
+        .field(
+            "🔥 Streak",
+            format!(
+                "`{}` days (best `{}`)",
+                stats.current_streak.unwrap_or(0),
+                stats.longest_streak.unwrap_or(0)
+            ),
+            true,
+        );
 
-    while let Some(button_interaction) = serenity::ComponentInteractionCollector::new(ctx)
-        .author_id(ctx.author().id)
-        .custom_ids(vec![cancel_id.clone(), confirm_id.clone()])
-        .timeout(Duration::from_secs(30))
-        .await
-    {
-        if button_interaction.data.custom_id.eq(&confirm_id) {
-            let result = sqlx::query!(
-                "UPDATE discord.users SET puffs = 0 WHERE user_id = $1",
-                BigDecimal::from(ctx.author().id.get())
+    if guild_id.is_some() {
+        embed = embed
+            .field(
+                "Server puffs",
+                format!("`{}`", stats.member_puffs.unwrap_or(0)),
+                true,
             )
-            .execute(&ctx.data().pool)
-            .await;
-
-            let mut embed = CreateEmbed::default();
-
-            if result.is_ok() {
-                embed = embed.title("Puffs Reset to 0!").color(Color::PURPLE);
-            } else {
-                embed = embed.title("Failed to reset puffs").color(Color::RED);
-            }
-
-            button_interaction
-                .create_response(
-                    ctx,
-                    serenity::CreateInteractionResponse::UpdateMessage(
-                        CreateInteractionResponseMessage::default()
-                            .embed(embed)
-                            .components(vec![]),
-                    ),
-                )
-                .await?;
-            break;
-        } else {
-            button_interaction
-                .create_response(
-                    ctx,
-                    serenity::CreateInteractionResponse::UpdateMessage(
-                        CreateInteractionResponseMessage::default()
-                            .embed(CreateEmbed::default().title("Canceled"))
-                            .components(vec![]),
-                    ),
-                )
-                .await?;
-            break;
-        }
+            .field(
+                "Total server puffs",
+                format!("`{}`", stats.guild_puffs.unwrap_or(0)),
+                true,
+            );
     }
 
+    ctx.send(CreateReply::default().embed(embed)).await?;
     Ok(())
 }
@@ -0,0 +1,176 @@
+use poise::{
+    serenity_prelude::{Color, CreateEmbed, UserId},
+    CreateReply,
+};
+use sqlx::{
+    types::{time::Time, BigDecimal},
+    PgExecutor,
+};
+
+use crate::types::{CommandResult, Context};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Achievement {
+    FirstPuff,
+    Puffs100,
+    Puffs420,
+    Puffs1000,
+    Streak7,
+    Streak30,
+    FourTwenty,
+}
+
+impl Achievement {
+    pub const ALL: [Self; 7] = [
+        Self::FirstPuff,
+        Self::Puffs100,
+        Self::Puffs420,
+        Self::Puffs1000,
+        Self::Streak7,
+        Self::Streak30,
+        Self::FourTwenty,
+    ];
+
+    /// Stored in `discord.user_achievements.achievement`, so these must never change
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::FirstPuff => "first_puff",
+            Self::Puffs100 => "puffs_100",
+            Self::Puffs420 => "puffs_420",
+            Self::Puffs1000 => "puffs_1000",
+            Self::Streak7 => "streak_7",
+            Self::Streak30 => "streak_30",
+            Self::FourTwenty => "four_twenty",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.id() == id)
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::FirstPuff => "🌱 First Puff",
+            Self::Puffs100 => "💨 Regular",
+            Self::Puffs420 => "🍃 Blazed",
+            Self::Puffs1000 => "👑 Cloud Chaser",
+            Self::Streak7 => "🔥 Week Streak",
+            Self::Streak30 => "☄️ Month Streak",
+            Self::FourTwenty => "🕓 Right On Time",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::FirstPuff => "Take your first puff",
+            Self::Puffs100 => "Take 100 puffs",
+            Self::Puffs420 => "Take 420 puffs",
+            Self::Puffs1000 => "Take 1,000 puffs",
+            Self::Streak7 => "Take a puff 7 days in a row",
+            Self::Streak30 => "Take a puff 30 days in a row",
+            Self::FourTwenty => "Take a puff at 4:20 server time (UTC in DMs)",
+        }
+    }
+
+    /// Whether a puff taken at the local time `now` that brought the user to `puffs` and `streak`
+    /// earns this
+    fn is_earned(&self, puffs: i64, streak: i32, now: Time) -> bool {
+        match self {
+            Self::FirstPuff => puffs >= 1,
+            Self::Puffs100 => puffs >= 100,
+            Self::Puffs420 => puffs >= 420,
+            Self::Puffs1000 => puffs >= 1000,
+            Self::Streak7 => streak >= 7,
+            Self::Streak30 => streak >= 30,
+            Self::FourTwenty => now.hour() % 12 == 4 && now.minute() == 20,
+        }
+    }
+}
+
+/// Stores every achievement earned by a puff taken at the local time `now`, returning the ones
+/// the user didn't have yet
+pub async fn award_achievements(
+    executor: impl PgExecutor<'_>,
+    user_id: &UserId,
+    puffs: i64,
+    streak: i32,
+    now: Time,
+) -> Result<Vec<Achievement>, sqlx::Error> {
+    let earned: Vec<String> = Achievement::ALL
+        .into_iter()
+        .filter(|a| a.is_earned(puffs, streak, now))
+        .map(|a| a.id().to_string())
+        .collect();
+
+    if earned.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let new = sqlx::query_scalar!(
+        r#"
+        INSERT INTO discord.user_achievements (user_id, achievement)
+        SELECT $1, UNNEST($2::TEXT[])
+        ON CONFLICT DO NOTHING
+        RETURNING achievement;"#,
+        BigDecimal::from(user_id.get()),
+        &earned
+    )
+    .fetch_all(executor)
+    .await?;
+
+    Ok(new
+        .iter()
+        .filter_map(|id| Achievement::from_id(id))
+        .collect())
+}
+
+/// List your earned and locked puff achievements
+#[poise::command(slash_command, rename = "achievements", user_cooldown = 5)]
+pub async fn puff_achievements(ctx: Context<'_>) -> CommandResult {
+    let earned = sqlx::query!(
+        r#"
+        SELECT achievement, earned_at
+        FROM discord.user_achievements
+        WHERE user_id = $1
+        ORDER BY earned_at ASC;"#,
+        BigDecimal::from(ctx.author().id.get())
+    )
+    .fetch_all(&ctx.data().pool)
+    .await?;
+
+    let mut unlocked = String::new();
+    let mut locked = String::new();
+    for achievement in Achievement::ALL {
+        match earned.iter().find(|e| e.achievement == achievement.id()) {
+            Some(e) => unlocked.push_str(&format!(
+                "**{}** - {} (<t:{}:d>)\n",
+                achievement.name(),
+                achievement.description(),
+                e.earned_at.unix_timestamp()
+            )),
+            None => locked.push_str(&format!(
+                "🔒 **{}** - {}\n",
+                achievement.name(),
+                achievement.description()
+            )),
+        }
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title(format!(
+            "Achievements - {}/{}",
+            Achievement::ALL.len() - locked.lines().count(),
+            Achievement::ALL.len()
+        ))
+        .color(Color::PURPLE);
+
+    if !unlocked.is_empty() {
+        embed = embed.field("Unlocked", unlocked, false);
+    }
+    if !locked.is_empty() {
+        embed = embed.field("Locked", locked, false);
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,138 @@
+use poise::{
+    serenity_prelude::{Color, CreateAttachment, CreateEmbed},
+    ChoiceParameter, CreateReply,
+};
+use sqlx::{types::BigDecimal, PgPool};
+
+use crate::{
+    chart::{bar_chart, Bar},
+    types::{CommandResult, Context},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum HistoryPeriod {
+    /// Last 24 hours, by hour
+    #[name = "Day"]
+    Day,
+    /// Last 7 days, by day
+    #[name = "Week"]
+    Week,
+    /// Last 30 days, by day
+    #[name = "Month"]
+    Month,
+}
+
+impl HistoryPeriod {
+    /// `date_trunc` unit for each bar, and how far back the first bar starts
+    fn buckets(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::Day => ("hour", "23 hours"),
+            Self::Week => ("day", "6 days"),
+            Self::Month => ("day", "29 days"),
+        }
+    }
+}
+
+struct Bucket {
+    start: sqlx::types::time::OffsetDateTime,
+    puffs: i64,
+}
+
+/// Puffs per hour or day (UTC) over the period, including empty buckets
+async fn fetch_history(
+    pool: &PgPool,
+    user_id: BigDecimal,
+    period: HistoryPeriod,
+) -> Result<Vec<Bucket>, sqlx::Error> {
+    let (unit, range) = period.buckets();
+
+    sqlx::query_as!(
+        Bucket,
+        r#"
+        SELECT
+            bucket AS "start!",
+            COUNT(e.id) AS "puffs!"
+        FROM
+            GENERATE_SERIES(
+                DATE_TRUNC($2, NOW()) - $3::TEXT::INTERVAL,
+                DATE_TRUNC($2, NOW()),
+                ('1 ' || $2)::INTERVAL
+            ) bucket
+            LEFT JOIN discord.puff_events e
+                ON e.user_id = $1
+                AND DATE_TRUNC($2, e.created_at) = bucket
+        GROUP BY bucket
+        ORDER BY bucket ASC;"#,
+        user_id,
+        unit,
+        range
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Chart your puffs over the last day, week or month
+#[poise::command(slash_command, rename = "history", user_cooldown = 10)]
+pub async fn puff_history(
+    ctx: Context<'_>,
+    #[description = "How far back to look"] period: Option<HistoryPeriod>,
+) -> CommandResult {
+    let period = period.unwrap_or(HistoryPeriod::Week);
+    let history = fetch_history(
+        &ctx.data().pool,
+        BigDecimal::from(ctx.author().id.get()),
+        period,
+    )
+    .await?;
+
+    let bars: Vec<Bar> = history
+        .iter()
+        .map(|bucket| Bar {
+            label: match period {
+                HistoryPeriod::Day => bucket.start.hour().to_string(),
+                HistoryPeriod::Week | HistoryPeriod::Month => bucket.start.day().to_string(),
+            },
+            value: bucket.puffs,
+        })
+        .collect();
+
+    let total: i64 = history.iter().map(|bucket| bucket.puffs).sum();
+    let bucket_name = match period {
+        HistoryPeriod::Day => "hour",
+        HistoryPeriod::Week | HistoryPeriod::Month => "day",
+    };
+
+    let mut description = format!(
+        "`{total}` puffs in the last {}",
+        period.name().to_lowercase()
+    );
+    if let Some(best) = history
+        .iter()
+        .filter(|b| b.puffs > 0)
+        .max_by_key(|b| b.puffs)
+    {
+        let when = match period {
+            HistoryPeriod::Day => format!("{:02}:00", best.start.hour()),
+            HistoryPeriod::Week | HistoryPeriod::Month => {
+                format!("{} {}", best.start.month(), best.start.day())
+            }
+        };
+        description.push_str(&format!(
+            "\nBusiest {bucket_name}: `{}` puffs ({when} UTC)",
+            best.puffs
+        ));
+    }
+
+    let image = bar_chart(&bars)?;
+    let embed = CreateEmbed::default()
+        .title(format!("{}'s puff history", ctx.author().name))
+        .description(description)
+        .image("attachment://history.png")
+        .color(Color::PURPLE);
+
+    let reply = CreateReply::default()
+        .embed(embed)
+        .attachment(CreateAttachment::bytes(image, "history.png"));
+    ctx.send(reply).await?;
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use poise::{
+    serenity_prelude::{Color, CreateEmbed, CreateEmbedFooter, GuildId},
+    ChoiceParameter,
+};
+use sqlx::{types::BigDecimal, PgPool};
+
+use crate::{
+    error_reply,
+    pagination::{page_count, paginate, Page},
+    types::{CommandError, CommandResult, Context},
+};
+
+/// Entries shown per page of the leaderboard
+const PAGE_SIZE: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum LeaderboardScope {
+    /// Every user, by total puffs
+    #[name = "Global"]
+    Global,
+    /// Members of this server, by puffs taken in it
+    #[name = "Server"]
+    Server,
+    /// Every server, by total server puffs
+    #[name = "Servers"]
+    Servers,
+}
+
+struct LeaderboardEntry {
+    /// User or guild ID
+    id: BigDecimal,
+    puffs: i64,
+    rank: i64,
+}
+
+/// One page of the leaderboard, along with the total number of ranked entries
+async fn fetch_page(
+    pool: &PgPool,
+    scope: LeaderboardScope,
+    guild_id: Option<GuildId>,
+    limit: i64,
+    offset: i64,
+) -> Result<(Vec<LeaderboardEntry>, i64), sqlx::Error> {
+    let rows = match scope {
+        LeaderboardScope::Server => sqlx::query!(
+            r#"
+            SELECT
+                m.user_id AS id,
+                m.puffs,
+                RANK() OVER (ORDER BY m.puffs DESC) AS "rank!",
+                COUNT(*) OVER () AS "total!"
+            FROM discord.guild_members m
+            JOIN discord.users u ON u.user_id = m.user_id
+            WHERE
+                m.guild_id = $1
+                AND NOT (u.is_blacklisted AND (u.blacklist_expires_at IS NULL OR u.blacklist_expires_at > NOW()))
+                AND m.puffs > 0
+            ORDER BY m.puffs DESC, m.user_id ASC
+            LIMIT $2
+            OFFSET $3;"#,
+            guild_id.map(|id| BigDecimal::from(id.get())),
+            limit,
+            offset
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            (
+                LeaderboardEntry {
+                    id: r.id,
+                    puffs: r.puffs,
+                    rank: r.rank,
+                },
+                r.total,
+            )
+        })
+        .collect::<Vec<_>>(),
+        LeaderboardScope::Global => sqlx::query!(
+            r#"
+            SELECT
+                user_id AS id,
+                puffs,
+                RANK() OVER (ORDER BY puffs DESC) AS "rank!",
+                COUNT(*) OVER () AS "total!"
+            FROM discord.users
+            WHERE
+                NOT (is_blacklisted AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()))
+                AND puffs > 0
+            ORDER BY puffs DESC, user_id ASC
+            LIMIT $1
+            OFFSET $2;"#,
+            limit,
+            offset
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            (
+                LeaderboardEntry {
+                    id: r.id,
+                    puffs: r.puffs,
+                    rank: r.rank,
+                },
+                r.total,
+            )
+        })
+        .collect::<Vec<_>>(),
+        LeaderboardScope::Servers => sqlx::query!(
+            r#"
+            SELECT
+                guild_id AS id,
+                puffs,
+                RANK() OVER (ORDER BY puffs DESC) AS "rank!",
+                COUNT(*) OVER () AS "total!"
+            FROM discord.guilds
+            WHERE
+                NOT (is_blacklisted AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()))
+                AND puffs > 0
+            ORDER BY puffs DESC, guild_id ASC
+            LIMIT $1
+            OFFSET $2;"#,
+            limit,
+            offset
+        )
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|r| {
+            (
+                LeaderboardEntry {
+                    id: r.id,
+                    puffs: r.puffs,
+                    rank: r.rank,
+                },
+                r.total,
+            )
+        })
+        .collect::<Vec<_>>(),
+    };
+
+    let total = rows.first().map(|(_, total)| *total).unwrap_or(0);
+    Ok((rows.into_iter().map(|(entry, _)| entry).collect(), total))
+}
+
+/// Rank of a user (or guild, for [`LeaderboardScope::Servers`]) on the leaderboard
+async fn fetch_rank(
+    pool: &PgPool,
+    scope: LeaderboardScope,
+    guild_id: Option<GuildId>,
+    id: u64,
+) -> Result<Option<LeaderboardEntry>, sqlx::Error> {
+    let id = BigDecimal::from(id);
+
+    let entry = match scope {
+        LeaderboardScope::Server => sqlx::query!(
+            r#"
+            SELECT id AS "id!", puffs AS "puffs!", rank AS "rank!"
+            FROM (
+                SELECT
+                    m.user_id AS id,
+                    m.puffs,
+                    RANK() OVER (ORDER BY m.puffs DESC) AS rank
+                FROM discord.guild_members m
+                JOIN discord.users u ON u.user_id = m.user_id
+                WHERE
+                    m.guild_id = $1
+                    AND NOT (u.is_blacklisted AND (u.blacklist_expires_at IS NULL OR u.blacklist_expires_at > NOW()))
+                    AND m.puffs > 0
+            ) ranked
+            WHERE id = $2;"#,
+            guild_id.map(|id| BigDecimal::from(id.get())),
+            id
+        )
+        .fetch_optional(pool)
+        .await?
+        .map(|r| LeaderboardEntry {
+            id: r.id,
+            puffs: r.puffs,
+            rank: r.rank,
+        }),
+        LeaderboardScope::Global => sqlx::query!(
+            r#"
+            SELECT id AS "id!", puffs AS "puffs!", rank AS "rank!"
+            FROM (
+                SELECT
+                    user_id AS id,
+                    puffs,
+                    RANK() OVER (ORDER BY puffs DESC) AS rank
+                FROM discord.users
+                WHERE
+                    NOT (is_blacklisted AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()))
+                    AND puffs > 0
+            ) ranked
+            WHERE id = $1;"#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?
+        .map(|r| LeaderboardEntry {
+            id: r.id,
+            puffs: r.puffs,
+            rank: r.rank,
+        }),
+        LeaderboardScope::Servers => sqlx::query!(
+            r#"
+            SELECT id AS "id!", puffs AS "puffs!", rank AS "rank!"
+            FROM (
+                SELECT
+                    guild_id AS id,
+                    puffs,
+                    RANK() OVER (ORDER BY puffs DESC) AS rank
+                FROM discord.guilds
+                WHERE
+                    NOT (is_blacklisted AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()))
+                    AND puffs > 0
+            ) ranked
+            WHERE id = $1;"#,
+            id
+        )
+        .fetch_optional(pool)
+        .await?
+        .map(|r| LeaderboardEntry {
+            id: r.id,
+            puffs: r.puffs,
+            rank: r.rank,
+        }),
+    };
+
+    Ok(entry)
+}
+
+fn medal(rank: i64) -> String {
+    match rank {
+        1 => "🥇".into(),
+        2 => "🥈".into(),
+        3 => "🥉".into(),
+        other => format!("`#{other}`"),
+    }
+}
+
+/// Shows the users, members or servers with the most puffs
+#[poise::command(slash_command, rename = "leaderboard", user_cooldown = 5)]
+pub async fn puff_leaderboard(
+    ctx: Context<'_>,
+    #[description = "Rank everyone, members of this server, or servers"] scope: Option<
+        LeaderboardScope,
+    >,
+) -> CommandResult {
+    let pool = &ctx.data().pool;
+    let guild_id = ctx.guild_id();
+    let scope = scope.unwrap_or(match guild_id {
+        Some(_) => LeaderboardScope::Server,
+        None => LeaderboardScope::Global,
+    });
+
+    if scope == LeaderboardScope::Server && guild_id.is_none() {
+        ctx.send(error_reply(
+            "Guild only leaderboard",
+            Some("The `Server` leaderboard can only be used in guilds (servers)"),
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let (title, own) = match scope {
+        LeaderboardScope::Server => (
+            "🏆 Server Member Puff Leaderboard",
+            fetch_rank(pool, scope, guild_id, ctx.author().id.get())
+                .await?
+                .map(|e| format!("You're {} with `{}` puffs", medal(e.rank), e.puffs)),
+        ),
+        LeaderboardScope::Global => (
+            "🏆 Global Puff Leaderboard",
+            fetch_rank(pool, scope, guild_id, ctx.author().id.get())
+                .await?
+                .map(|e| format!("You're {} with `{}` puffs", medal(e.rank), e.puffs)),
+        ),
+        LeaderboardScope::Servers => (
+            "🏆 Top Servers Puff Leaderboard",
+            match guild_id {
+                Some(id) => fetch_rank(pool, scope, guild_id, id.get())
+                    .await?
+                    .map(|e| format!("This server is {} with `{}` puffs", medal(e.rank), e.puffs)),
+                None => None,
+            },
+        ),
+    };
+    let own = own.unwrap_or_else(|| "Not ranked yet, try `/puff take`".into());
+
+    let own = &own;
+    paginate(ctx, |page| async move {
+        let (entries, total) = fetch_page(
+            pool,
+            scope,
+            guild_id,
+            PAGE_SIZE,
+            i64::from(page) * PAGE_SIZE,
+        )
+        .await?;
+
+        let mut description = String::new();
+        for entry in &entries {
+            let name = match scope {
+                LeaderboardScope::Global | LeaderboardScope::Server => {
+                    format!("<@{}>", entry.id)
+                }
+                LeaderboardScope::Servers => guild_name(ctx, &entry.id),
+            };
+            description.push_str(&format!(
+                "{} {} - `{}` puffs\n",
+                medal(entry.rank),
+                name,
+                entry.puffs
+            ));
+        }
+        if entries.is_empty() {
+            description.push_str("Nobody has taken a puff yet\n");
+        }
+        description.push_str(&format!("\n{own}"));
+
+        let embed = CreateEmbed::default()
+            .title(title)
+            .description(description)
+            .color(Color::PURPLE)
+            .footer(CreateEmbedFooter::new(format!(
+                "Page {}/{}",
+                page + 1,
+                page_count(total, PAGE_SIZE)
+            )));
+
+        Ok::<_, CommandError>(Page {
+            embed,
+            total_pages: page_count(total, PAGE_SIZE),
+        })
+    })
+    .await
+}
+
+fn guild_name(ctx: Context<'_>, id: &BigDecimal) -> String {
+    id.to_string()
+        .parse::<u64>()
+        .ok()
+        .and_then(|id| GuildId::new(id).name(ctx.cache()))
+        .map(|name| format!("**{name}**"))
+        .unwrap_or_else(|| "*Unknown server*".into())
+}
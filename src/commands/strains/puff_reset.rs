@@ -0,0 +1,325 @@
+use std::time::Duration;
+
+use poise::{
+    serenity_prelude::{
+        self as serenity, ButtonStyle, Color, CreateActionRow, CreateButton, CreateEmbed,
+        CreateInteractionResponseMessage, GuildId, ReactionType, UserId,
+    },
+    ChoiceParameter, CreateReply,
+};
+use sqlx::{
+    types::{time::OffsetDateTime, BigDecimal},
+    PgPool,
+};
+use tracing::error;
+
+use super::puff::insert_user;
+use crate::{
+    error_reply,
+    types::{CommandError, CommandResult, Context},
+};
+
+/// How long a reset can be undone for
+const UNDO_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum ResetScope {
+    /// Your total puffs
+    #[name = "Everywhere"]
+    Global,
+    /// Only your puffs in this server
+    #[name = "This server"]
+    Server,
+}
+
+/// The guild a scope applies to, which is `None` for [`ResetScope::Global`].
+///
+/// Tells the user and returns `None` if they picked [`ResetScope::Server`] outside a guild
+async fn scope_guild(
+    ctx: Context<'_>,
+    scope: ResetScope,
+) -> Result<Option<Option<GuildId>>, CommandError> {
+    match (scope, ctx.guild_id()) {
+        (ResetScope::Global, _) => Ok(Some(None)),
+        (ResetScope::Server, Some(guild_id)) => Ok(Some(Some(guild_id))),
+        (ResetScope::Server, None) => {
+            ctx.send(error_reply(
+                "Guild only reset",
+                Some("`This server` resets can only be used in guilds (servers)"),
+            ))
+            .await?;
+            Ok(None)
+        }
+    }
+}
+
+/// Sets the user's global or member puffs to 0, keeping the old count until `UNDO_WINDOW` passes.
+///
+/// Returns when the snapshot expires, or `None` if there were no puffs to reset
+async fn reset_puffs(
+    pool: &PgPool,
+    user_id: &UserId,
+    guild_id: Option<&GuildId>,
+) -> Result<Option<OffsetDateTime>, CommandError> {
+    let user_id = BigDecimal::from(user_id.get());
+    let guild_id = guild_id.map(|id| BigDecimal::from(id.get()));
+    let mut tx = pool.begin().await?;
+
+    let old_puffs = match &guild_id {
+        Some(guild_id) => {
+            sqlx::query_scalar!(
+                r#"
+                UPDATE discord.guild_members m
+                SET puffs = 0
+                FROM (
+                    SELECT puffs
+                    FROM discord.guild_members
+                    WHERE guild_id = $1 AND user_id = $2
+                    FOR UPDATE
+                ) old
+                WHERE
+                    m.guild_id = $1
+                    AND m.user_id = $2
+                    AND old.puffs > 0
+                RETURNING old.puffs;"#,
+                guild_id,
+                user_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        }
+        None => {
+            sqlx::query_scalar!(
+                r#"
+                UPDATE discord.users u
+                SET puffs = 0
+                FROM (
+                    SELECT puffs
+                    FROM discord.users
+                    WHERE user_id = $1
+                    FOR UPDATE
+                ) old
+                WHERE
+                    u.user_id = $1
+                    AND old.puffs > 0
+                RETURNING old.puffs;"#,
+                user_id
+            )
+            .fetch_optional(&mut *tx)
+            .await?
+        }
+    };
+
+    let Some(old_puffs) = old_puffs else {
+        return Ok(None);
+    };
+
+    // Earlier snapshots are kept, so resetting again can't lose the puffs they hold
+    let expires_at = sqlx::query_scalar!(
+        r#"
+        INSERT INTO discord.puff_resets (user_id, guild_id, puffs, expires_at)
+        VALUES ($1, $2, $3, NOW() + make_interval(secs => $4))
+        RETURNING expires_at;"#,
+        user_id,
+        guild_id,
+        old_puffs,
+        UNDO_WINDOW.as_secs_f64()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(Some(expires_at))
+}
+
+/// Reset your puff count to 0!
+///
+/// The old count is kept for 24 hours, and can be restored with `/puff undo-reset`
+#[poise::command(slash_command, rename = "reset", user_cooldown = 30)]
+pub async fn puff_reset(
+    ctx: Context<'_>,
+    #[description = "Reset your total puffs, or only your puffs in this server"] scope: Option<
+        ResetScope,
+    >,
+) -> CommandResult {
+    let scope = scope.unwrap_or(ResetScope::Global);
+    let Some(guild_id) = scope_guild(ctx, scope).await? else {
+        return Ok(());
+    };
+
+    let counter = match scope {
+        ResetScope::Global => "total puff count",
+        ResetScope::Server => "puff count in this server",
+    };
+    let initial_embed = CreateEmbed::default()
+        .title("Are you sure?")
+        .description(format!(
+            "This will set your {counter} to 0. You can undo this with `/puff undo-reset` for the next 24 hours"
+        ));
+
+    let cancel_id = format!("{}-puff-reset-cancel", ctx.id());
+    let confirm_id = format!("{}-puff-reset-confirm", ctx.id());
+
+    let buttons = vec![
+        CreateButton::new(&cancel_id)
+            .style(ButtonStyle::Primary)
+            .label("Cancel")
+            .emoji(ReactionType::Unicode("✖️".into())),
+        CreateButton::new(&confirm_id)
+            .label("Reset")
+            .style(ButtonStyle::Danger)
+            .emoji(ReactionType::Unicode("⚠️".into())),
+    ];
+
+    let reply = CreateReply::default()
+        .embed(initial_embed)
+        .components(vec![CreateActionRow::Buttons(buttons)]);
+
+    ctx.send(reply).await?;
+
+    if let Some(button_interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .custom_ids(vec![cancel_id.clone(), confirm_id.clone()])
+        .timeout(Duration::from_secs(30))
+        .await
+    {
+        let embed = if button_interaction.data.custom_id.eq(&confirm_id) {
+            match reset_puffs(&ctx.data().pool, &ctx.author().id, guild_id.as_ref()).await {
+                Ok(Some(expires_at)) => CreateEmbed::default()
+                    .title("Puffs Reset to 0!")
+                    .description(format!(
+                        "Changed your mind? Use `/puff undo-reset` before <t:{}:f>",
+                        expires_at.unix_timestamp()
+                    ))
+                    .color(Color::PURPLE),
+                Ok(None) => CreateEmbed::default()
+                    .title("Nothing to reset")
+                    .description(format!("Your {counter} is already 0"))
+                    .color(Color::PURPLE),
+                Err(error) => {
+                    error!("Failed to reset puffs for {}: {:?}", ctx.author().id, error);
+                    CreateEmbed::default()
+                        .title("Failed to reset puffs")
+                        .color(Color::RED)
+                }
+            }
+        } else {
+            CreateEmbed::default().title("Canceled")
+        };
+
+        button_interaction
+            .create_response(
+                ctx,
+                serenity::CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::default()
+                        .embed(embed)
+                        .components(vec![]),
+                ),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Restore the puffs from your resets
+///
+/// Resets can be undone for 24 hours. Puffs taken since the reset are kept
+#[poise::command(slash_command, rename = "undo-reset", user_cooldown = 5)]
+pub async fn puff_undo_reset(
+    ctx: Context<'_>,
+    #[description = "Restore your total puffs, or only your puffs in this server"] scope: Option<
+        ResetScope,
+    >,
+) -> CommandResult {
+    let scope = scope.unwrap_or(ResetScope::Global);
+    let Some(guild_id) = scope_guild(ctx, scope).await? else {
+        return Ok(());
+    };
+
+    let user_id = ctx.author().id;
+    let guild_id = guild_id.map(|id| BigDecimal::from(id.get()));
+    let mut tx = ctx.data().pool.begin().await?;
+
+    // Every unexpired reset of the scope is undone together
+    let puffs = sqlx::query_scalar!(
+        r#"
+        WITH undone AS (
+            DELETE FROM discord.puff_resets
+            WHERE
+                user_id = $1
+                AND guild_id IS NOT DISTINCT FROM $2
+                AND expires_at > NOW()
+            RETURNING puffs
+        )
+        SELECT SUM(puffs)::BIGINT AS puffs
+        FROM undone;"#,
+        BigDecimal::from(user_id.get()),
+        guild_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let Some(puffs) = puffs else {
+        let counter = match scope {
+            ResetScope::Global => "total puffs",
+            ResetScope::Server => "puffs in this server",
+        };
+        ctx.send(error_reply(
+            "Nothing to undo",
+            Some(&format!(
+                "You haven't reset your {counter} in the last 24 hours"
+            )),
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    insert_user(&mut *tx, &user_id).await?;
+    let restored = match &guild_id {
+        Some(guild_id) => {
+            sqlx::query_scalar!(
+                r#"
+                INSERT INTO discord.guild_members (guild_id, user_id, puffs)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (guild_id, user_id) DO UPDATE
+                SET puffs = guild_members.puffs + EXCLUDED.puffs
+                RETURNING puffs;"#,
+                guild_id,
+                BigDecimal::from(user_id.get()),
+                puffs
+            )
+            .fetch_one(&mut *tx)
+            .await?
+        }
+        None => {
+            sqlx::query_scalar!(
+                r#"
+                UPDATE discord.users
+                SET puffs = puffs + $2
+                WHERE user_id = $1
+                RETURNING puffs;"#,
+                BigDecimal::from(user_id.get()),
+                puffs
+            )
+            .fetch_one(&mut *tx)
+            .await?
+        }
+    };
+
+    tx.commit().await?;
+
+    let title = match scope {
+        ResetScope::Server => format!("Server puffs restored - {restored}"),
+        ResetScope::Global => format!("Total puffs restored - {restored}"),
+    };
+    let embed = CreateEmbed::default()
+        .title(title)
+        .description(format!(
+            "Brought back `{puffs}` puffs from your resets in the last 24 hours"
+        ))
+        .color(Color::PURPLE);
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use poise::{
+    serenity_prelude::{Color, CreateEmbed, CreateEmbedAuthor},
+    CreateReply,
+};
+use rand::Rng;
+use sqlx::{
+    types::{
+        time::{Date, OffsetDateTime},
+        BigDecimal,
+    },
+    PgPool,
+};
+
+use super::{
+    fetch_strain::{fetch_strain, strain_embed},
+    search_strains::{parse_list, SearchFilter, Subspecies},
+};
+use crate::types::{CommandError, CommandResult, Context};
+
+/// ID of the strain at `index` of the strains matching `filter`, wrapping around
+async fn strain_id_at(
+    pool: &PgPool,
+    filter: &SearchFilter,
+    index: u64,
+) -> Result<Option<i64>, sqlx::Error> {
+    let total = filter.count(pool).await?;
+    if total == 0 {
+        return Ok(None);
+    }
+
+    let offset = (index % total as u64) as i64;
+    let page = filter.fetch_page(pool, 1, offset).await?;
+    Ok(page.strains.first().map(|strain| strain.id))
+}
+
+/// Picks the strain at `index` of the strains matching `filter`, wrapping around
+async fn strain_at(
+    pool: &PgPool,
+    filter: &SearchFilter,
+    index: u64,
+) -> Result<Option<CreateEmbed>, CommandError> {
+    let Some(id) = strain_id_at(pool, filter, index).await? else {
+        return Ok(None);
+    };
+
+    Ok(fetch_strain(pool, id).await?.map(strain_embed))
+}
+
+/// Index of the strain for a (UTC) date, hashed from its julian day with SplitMix64 so it never
+/// depends on a random number generator's implementation
+pub fn daily_index(date: Date) -> u64 {
+    let mut z = (date.to_julian_day() as u64).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+/// ID of the strain of the day for `date`, picking and storing it if it hasn't been yet.
+///
+/// Once stored, the pick stays the same even if strains are imported later
+async fn daily_strain_id(pool: &PgPool, date: Date) -> Result<Option<i64>, sqlx::Error> {
+    let stored = sqlx::query_scalar!(
+        "SELECT strain_id FROM public.daily_strains WHERE day = $1;",
+        date
+    )
+    .fetch_optional(pool)
+    .await?;
+    if stored.is_some() {
+        return Ok(stored);
+    }
+
+    // Counted and picked in one statement, so an import can't shift the index in between.
+    // Another shard or scheduled post may have picked first, in which case its pick is kept
+    sqlx::query_scalar!(
+        r#"
+        WITH picked AS (
+            SELECT id
+            FROM public.strains
+            ORDER BY id ASC
+            LIMIT 1
+            OFFSET (SELECT MOD($2::NUMERIC, NULLIF(COUNT(*), 0))::BIGINT FROM public.strains)
+        )
+        INSERT INTO public.daily_strains (day, strain_id)
+        SELECT $1, id FROM picked
+        ON CONFLICT (day) DO UPDATE SET day = EXCLUDED.day
+        RETURNING strain_id;"#,
+        date,
+        BigDecimal::from(daily_index(date))
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// The strain of the day, shared by `/strain daily` and scheduled posts
+pub async fn daily_strain(pool: &PgPool) -> Result<Option<CreateEmbed>, CommandError> {
+    let Some(id) = daily_strain_id(pool, OffsetDateTime::now_utc().date()).await? else {
+        return Ok(None);
+    };
+    let embed = fetch_strain(pool, id).await?.map(strain_embed);
+
+    Ok(embed.map(|e| e.author(CreateEmbedAuthor::new("🌿 Strain of the Day"))))
+}
+
+/// Fetches a random strain, optionally matching a filter
+#[poise::command(
+    slash_command,
+    rename = "random",
+    required_bot_permissions = "SEND_MESSAGES",
+    nsfw_only = true
+)]
+pub async fn strain_random(
+    ctx: Context<'_>,
+    #[description = "Indica, Sativa, Hybrid, or Ruderalis"] subspecies: Option<Subspecies>,
+    #[description = "Reported strain effects, separated by commas"]
+    #[autocomplete = "super::search_strains::autocomplete_effects"]
+    effects: Option<String>,
+    #[description = "Reported strain flavors, separated by commas"]
+    #[autocomplete = "super::search_strains::autocomplete_flavors"]
+    flavors: Option<String>,
+) -> CommandResult {
+    let filter = SearchFilter {
+        subspecies,
+        effects: parse_list(effects.as_deref()),
+        flavors: parse_list(flavors.as_deref()),
+        ..Default::default()
+    };
+
+    let index = rand::thread_rng().gen();
+    let reply = match strain_at(&ctx.data().pool, &filter, index).await? {
+        Some(embed) => {
+            CreateReply::default().embed(embed.author(CreateEmbedAuthor::new("🎲 Random Strain")))
+        }
+        None => CreateReply::default().embed(
+            CreateEmbed::default()
+                .title("No Strains found")
+                .description("Try broadening your search filters")
+                .color(Color::RED),
+        ),
+    };
+
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+/// Today's strain, the same for everyone
+#[poise::command(
+    slash_command,
+    rename = "daily",
+    required_bot_permissions = "SEND_MESSAGES",
+    nsfw_only = true
+)]
+pub async fn strain_daily(ctx: Context<'_>) -> CommandResult {
+    let reply = match daily_strain(&ctx.data().pool).await? {
+        Some(embed) => CreateReply::default().embed(embed),
+        None => CreateReply::default().embed(
+            CreateEmbed::default()
+                .title("No Strains found")
+                .color(Color::RED),
+        ),
+    };
+
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn daily_index_is_stable() {
+        // SplitMix64 of the julian day, these must never change
+        // 2026-10-18
+        let date = Date::from_julian_day(2_461_332).unwrap();
+        assert_eq!(daily_index(date), 12_013_113_086_016_348_570);
+
+        // 2000-01-01
+        let date = Date::from_julian_day(2_451_545).unwrap();
+        assert_eq!(daily_index(date), 6_685_406_293_438_958_844);
+    }
+
+    #[test]
+    fn daily_index_changes_every_day() {
+        let date = Date::from_julian_day(2_461_332).unwrap();
+        assert_ne!(daily_index(date), daily_index(date.next_day().unwrap()));
+    }
+}
@@ -1,15 +1,19 @@
+use std::time::Duration;
+
 use poise::{
-    serenity_prelude::{Color, CreateEmbed},
+    serenity_prelude::{Color, CreateEmbed, CreateEmbedFooter},
     ChoiceParameter, CreateReply,
 };
 use serde::{Deserialize, Serialize};
-use sqlx::Type;
-use tokio::sync::OnceCell;
-use tracing::debug;
+use sqlx::{PgPool, Type};
 
-use crate::types::{CommandResult, Context};
+use crate::{
+    cache::RefreshCache,
+    pagination::{page_count, paginate, Page},
+    types::{CommandResult, Context},
+};
 
-#[derive(Debug, Type, Deserialize, Serialize, ChoiceParameter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Type, Deserialize, Serialize, ChoiceParameter)]
 #[sqlx(type_name = "subspecies", rename_all = "lowercase")]
 pub enum Subspecies {
     Hybrid,
@@ -33,6 +37,215 @@ impl std::fmt::Display for Subspecies {
     }
 }
 
+impl std::str::FromStr for Subspecies {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "hybrid" => Ok(Self::Hybrid),
+            "indica" => Ok(Self::Indica),
+            "sativa" => Ok(Self::Sativa),
+            "ruderalis" => Ok(Self::Ruderalis),
+            other => Err(anyhow::anyhow!("Unknown subspecies: {other}")),
+        }
+    }
+}
+
+/// Strains shown per page of `/search`
+const PAGE_SIZE: i64 = 15;
+
+/// Whether a strain needs every listed value, or just one of them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ChoiceParameter)]
+pub enum FilterMatch {
+    #[default]
+    #[name = "All of"]
+    All,
+    #[name = "Any of"]
+    Any,
+}
+
+/// Filters shared by strain lookups like `/search`
+///
+/// List filters are lowercase and matched case-insensitively
+#[derive(Debug, Default, Clone)]
+pub struct SearchFilter {
+    pub name: Option<String>,
+    pub subspecies: Option<Subspecies>,
+    pub flavors: Vec<String>,
+    pub effects: Vec<String>,
+    pub ailments: Vec<String>,
+    /// Strains with any of these effects are left out
+    pub exclude_effects: Vec<String>,
+    pub match_mode: FilterMatch,
+}
+
+/// Marks positive effects in autocomplete choices
+pub const POSITIVE_LABEL: &str = "🔺";
+/// Marks negative effects in autocomplete choices
+pub const NEGATIVE_LABEL: &str = "🔻";
+
+/// Removes the polarity label autocomplete puts in front of effects
+fn strip_label(value: &str) -> &str {
+    value
+        .trim()
+        .trim_start_matches(POSITIVE_LABEL)
+        .trim_start_matches(NEGATIVE_LABEL)
+        .trim_start()
+}
+
+/// Splits a comma separated option like `"Relaxed, Sleepy"` into lowercase values.
+///
+/// Polarity labels from effect autocompletes are ignored
+pub fn parse_list(value: Option<&str>) -> Vec<String> {
+    let mut values: Vec<String> = Vec::new();
+    for value in value.unwrap_or_default().split(',') {
+        let value = strip_label(value).to_lowercase();
+        if !value.is_empty() && !values.contains(&value) {
+            values.push(value);
+        }
+    }
+    values
+}
+
+pub struct SearchResult {
+    pub id: i64,
+    pub name: String,
+}
+
+/// One page of strains matching a [`SearchFilter`]
+pub struct SearchPage {
+    pub strains: Vec<SearchResult>,
+    /// Number of strains matching the filter, across every page
+    pub total: i64,
+    /// Where the page starts, after clamping to the last page
+    pub offset: i64,
+}
+
+impl SearchFilter {
+    /// Fetches one page of matching strains along with the total number of matches.
+    ///
+    /// An `offset` past the last match is clamped to the start of the last page, so the page is
+    /// only empty when nothing matches
+    pub async fn fetch_page(
+        &self,
+        pool: &PgPool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<SearchPage, sqlx::Error> {
+        let match_all = self.match_mode == FilterMatch::All;
+
+        let rows = sqlx::query!(
+            r#"
+            WITH matches AS (
+                SELECT
+                    s.id,
+                    s.name,
+                    SIMILARITY(s.name, COALESCE($1, '')) AS similarity
+                FROM
+                    public.strains s
+                WHERE
+                    (s.name ILIKE ('%' || $1 || '%') OR s.name % $1 OR $1 IS NULL)
+                    AND (s.subspecies = $2 OR $2 IS NULL)
+                    AND (
+                        CARDINALITY($3::TEXT[]) = 0
+                        OR (
+                            SELECT COUNT(*)
+                            FROM public.strain_flavors sf
+                            JOIN public.unique_flavors uf ON sf.flavor_id = uf.id
+                            WHERE sf.strain_id = s.id AND LOWER(uf.flavor) = ANY($3)
+                        ) >= CASE WHEN $6 THEN CARDINALITY($3) ELSE 1 END
+                    )
+                    AND (
+                        CARDINALITY($4::TEXT[]) = 0
+                        OR (
+                            SELECT COUNT(*)
+                            FROM public.strain_effects se
+                            JOIN public.unique_effects ue ON se.effect_id = ue.id
+                            WHERE se.strain_id = s.id AND LOWER(ue.effect) = ANY($4)
+                        ) >= CASE WHEN $6 THEN CARDINALITY($4) ELSE 1 END
+                    )
+                    AND (
+                        CARDINALITY($5::TEXT[]) = 0
+                        OR (
+                            SELECT COUNT(*)
+                            FROM public.strain_ailments sa
+                            JOIN public.unique_ailments ua ON sa.ailment_id = ua.id
+                            WHERE sa.strain_id = s.id AND LOWER(ua.ailment) = ANY($5)
+                        ) >= CASE WHEN $6 THEN CARDINALITY($5) ELSE 1 END
+                    )
+                    AND NOT EXISTS (
+                        SELECT 1
+                        FROM public.strain_effects se
+                        JOIN public.unique_effects ue ON se.effect_id = ue.id
+                        WHERE se.strain_id = s.id AND LOWER(ue.effect) = ANY($7::TEXT[])
+                    )
+            )
+            SELECT
+                id AS "id!",
+                name AS "name!",
+                COUNT(*) OVER () AS "total!"
+            FROM
+                matches
+            ORDER BY
+                -- Closest names first when searching by name
+                similarity DESC,
+                id ASC
+            LIMIT $8
+            -- Start of the last page if the offset is past it
+            OFFSET (SELECT LEAST($9, GREATEST(COUNT(*) - 1, 0) / $8 * $8) FROM matches);
+            "#,
+            self.name,
+            self.subspecies as _,
+            &self.flavors,
+            &self.effects,
+            &self.ailments,
+            match_all,
+            &self.exclude_effects,
+            limit,
+            offset
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let total = rows.first().map(|r| r.total).unwrap_or(0);
+        let strains = rows
+            .into_iter()
+            .map(|r| SearchResult {
+                id: r.id,
+                name: r.name,
+            })
+            .collect();
+
+        Ok(SearchPage {
+            strains,
+            total,
+            offset: offset.min((total - 1).max(0) / limit * limit),
+        })
+    }
+
+    /// Total number of strains matching the filter
+    pub async fn count(&self, pool: &PgPool) -> Result<i64, sqlx::Error> {
+        Ok(self.fetch_page(pool, 1, 0).await?.total)
+    }
+}
+
+/// Closest strain name to `name`, ignoring every other filter.
+///
+/// Never `name` itself, which can still exist when other filters left it out
+pub async fn did_you_mean(pool: &PgPool, name: &str) -> Result<Option<String>, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"
+        SELECT name
+        FROM public.strains
+        WHERE SIMILARITY(name, $1) > 0.1 AND LOWER(name) <> LOWER(TRIM($1))
+        ORDER BY SIMILARITY(name, $1) DESC, id ASC
+        LIMIT 1;"#,
+        name
+    )
+    .fetch_optional(pool)
+    .await
+}
+
 /// Searches strains with a filter
 #[poise::command(
     slash_command,
@@ -40,173 +253,383 @@ impl std::fmt::Display for Subspecies {
     nsfw_only = true,
     category = "Strains"
 )]
+#[allow(clippy::too_many_arguments)]
 pub async fn search(
     ctx: Context<'_>,
-    #[description = "Name of the strain"] name: Option<String>,
+    #[description = "Name of the strain"]
+    #[autocomplete = "autocomplete_strain_names"]
+    name: Option<String>,
     #[description = "Indica, Sativa, Hybrid, or Ruderalis"] subspecies: Option<Subspecies>,
-    #[description = "Reported strain flavors"]
+    // Options from before lists were supported keep their singular names, so existing
+    // invocations still work
+    #[description = "Reported strain flavors, separated by commas"]
     #[autocomplete = "autocomplete_flavors"]
-    flavor: Option<String>,
-    #[description = "Reported strain effects"]
-    #[autocomplete = "autocomplete_effects"]
-    effect: Option<String>,
-    #[description = "Reported strain ailments"]
+    #[rename = "flavor"]
+    flavors: Option<String>,
+    #[description = "Reported positive effects, separated by commas"]
+    #[autocomplete = "autocomplete_positive_effects"]
+    #[rename = "effect"]
+    effects: Option<String>,
+    #[description = "Reported negative effects, separated by commas"]
+    #[autocomplete = "autocomplete_negative_effects"]
+    negative_effects: Option<String>,
+    #[description = "Reported strain ailments, separated by commas"]
     #[autocomplete = "autocomplete_ailments"]
-    ailment: Option<String>,
+    #[rename = "ailment"]
+    ailments: Option<String>,
+    #[description = "Leave out strains with any of these effects, separated by commas"]
+    #[autocomplete = "autocomplete_negative_effects"]
+    exclude_effects: Option<String>,
+    #[description = "Whether strains need all of the listed flavors, effects and ailments, or any of them"]
+    #[rename = "match"]
+    match_mode: Option<FilterMatch>,
 ) -> CommandResult {
     let pool = &ctx.data().pool;
 
-    let result = sqlx::query!(
-        r#"
-        SELECT DISTINCT
-            s.id,
-            s.NAME
-        FROM
-            public.strains s
-
-            -- Flavors
-            LEFT JOIN public.strain_flavors sf ON s.id = sf.strain_id
-            LEFT JOIN public.unique_flavors uf ON sf.flavor_id = uf.id
-
-            -- Effects
-            LEFT JOIN public.strain_effects se ON s.id = se.strain_id
-            LEFT JOIN public.unique_effects ue ON se.effect_id = ue.id
-
-            -- Ailments
-            LEFT JOIN public.strain_ailments sa ON s.id = sa.strain_id
-            LEFT JOIN public.unique_ailments ua ON sa.ailment_id = ua.id
-        WHERE
-            (s.NAME ILIKE ('%' || $1 || '%') OR $1 IS NULL)
-            AND (s.subspecies = $2 OR $2 IS NULL)
-            AND (uf.flavor ILIKE $3 OR $3 IS NULL)
-            AND (ue.effect ILIKE $4 OR $4 IS NULL)
-            AND (ua.ailment ILIKE $5 OR $5 IS NULL)
-        ORDER BY
-            s.id ASC
-        LIMIT
-            15;
-        "#,
+    // Both options filter on the same effects, they're only split to make them easier to pick from
+    let mut effects = parse_list(effects.as_deref());
+    for effect in parse_list(negative_effects.as_deref()) {
+        if !effects.contains(&effect) {
+            effects.push(effect);
+        }
+    }
+
+    let filter = SearchFilter {
         name,
-        subspecies as _,
-        flavor,
-        effect,
-        ailment
-    )
-    .fetch_all(pool)
-    .await?;
+        subspecies,
+        flavors: parse_list(flavors.as_deref()),
+        effects,
+        ailments: parse_list(ailments.as_deref()),
+        exclude_effects: parse_list(exclude_effects.as_deref()),
+        match_mode: match_mode.unwrap_or_default(),
+    };
+
+    let first_page = filter.fetch_page(pool, PAGE_SIZE, 0).await?;
+    if first_page.total == 0 {
+        let mut description = String::from("Try broadening your search filters");
+        if let Some(name) = &filter.name {
+            if let Some(suggestion) = did_you_mean(pool, name).await? {
+                description = format!("Did you mean **{suggestion}**?\n\n{description}");
+            }
+        }
 
-    if result.is_empty() {
         let embed = CreateEmbed::default()
             .title("No Strains found")
-            .description("Try broadening your seach filters")
+            .description(description)
             .color(Color::RED);
         let reply = CreateReply::default().embed(embed);
         ctx.send(reply).await?;
         return Ok(());
     }
-    let mut embed = CreateEmbed::default();
-    let mut description = String::new();
-
-    for strain in result {
-        description.push_str(&format!("- `{}`: **{}**", strain.id, strain.name));
-        description.push('\n');
-    }
 
-    let title = match name {
+    let title = match &filter.name {
         Some(t) => format!("Strains matching: \"{t}\""),
         None => "Strains".to_string(),
     };
 
-    embed = embed
-        .title(title)
-        .description(description)
-        .color(Color::PURPLE);
-    let reply = CreateReply::default().embed(embed);
-    ctx.send(reply).await?;
-    Ok(())
-}
-
-static FLAVORS: OnceCell<Vec<String>> = OnceCell::const_new();
-static EFFECTS: OnceCell<Vec<String>> = OnceCell::const_new();
-static AILMENTS: OnceCell<Vec<String>> = OnceCell::const_new();
-
-async fn autocomplete_flavors(ctx: Context<'_>, searching: &str) -> Vec<String> {
-    let flavors = FLAVORS
-        .get_or_init(|| async {
-            debug!("Fetched flavors");
-            sqlx::query_scalar!(
-                "SELECT DISTINCT flavor
-                FROM public.unique_flavors
-                ORDER BY flavor ASC
-                LIMIT 100;"
-            )
-            .fetch_all(&ctx.data().pool)
-            .await
-            .unwrap_or_default()
-        })
-        .await;
+    let filter = &filter;
+    let title = &title;
+    // Already fetched to check for matches, so it's only queried again when going back to it
+    let mut first_page = Some(first_page);
+    paginate(ctx, |page| {
+        let fetched = first_page.take().filter(|_| page == 0);
+        async move {
+            let results = match fetched {
+                Some(results) => results,
+                None => {
+                    filter
+                        .fetch_page(pool, PAGE_SIZE, i64::from(page) * PAGE_SIZE)
+                        .await?
+                }
+            };
+
+            let mut description = String::new();
+            for strain in results.strains {
+                description.push_str(&format!("- `{}`: **{}**", strain.id, strain.name));
+                description.push('\n');
+            }
+
+            let total_pages = page_count(results.total, PAGE_SIZE);
+            let embed = CreateEmbed::default()
+                .title(title)
+                .description(description)
+                .color(Color::PURPLE)
+                .footer(CreateEmbedFooter::new(format!(
+                    "{} strains • Page {}/{}",
+                    results.total,
+                    results.offset / PAGE_SIZE + 1,
+                    total_pages
+                )));
+
+            Ok(Page { embed, total_pages })
+        }
+    })
+    .await
+}
+
+/// How long autocomplete lists are served before being refreshed
+const LIST_TTL: Duration = Duration::from_secs(60 * 60);
+
+pub static FLAVORS: RefreshCache<Vec<String>> = RefreshCache::new("flavors", LIST_TTL, |pool| {
+    Box::pin(async move {
+        sqlx::query_scalar!(
+            "SELECT DISTINCT flavor
+            FROM public.unique_flavors
+            ORDER BY flavor ASC;"
+        )
+        .fetch_all(&pool)
+        .await
+    })
+});
+
+pub struct Effect {
+    pub name: String,
+    pub is_positive: bool,
+}
+
+impl Effect {
+    /// The name with its polarity, e.g. "🔺 Relaxed"
+    fn label(&self) -> String {
+        let label = match self.is_positive {
+            true => POSITIVE_LABEL,
+            false => NEGATIVE_LABEL,
+        };
+        format!("{label} {}", self.name)
+    }
+}
+
+pub static EFFECTS: RefreshCache<Vec<Effect>> = RefreshCache::new("effects", LIST_TTL, |pool| {
+    Box::pin(async move {
+        sqlx::query_as!(
+            Effect,
+            "SELECT effect AS name, is_positive
+            FROM public.unique_effects
+            ORDER BY effect ASC;"
+        )
+        .fetch_all(&pool)
+        .await
+    })
+});
+
+pub static AILMENTS: RefreshCache<Vec<String>> = RefreshCache::new("ailments", LIST_TTL, |pool| {
+    Box::pin(async move {
+        sqlx::query_scalar!(
+            "SELECT DISTINCT ailment
+            FROM public.unique_ailments
+            ORDER BY ailment ASC;"
+        )
+        .fetch_all(&pool)
+        .await
+    })
+});
 
+/// Strain names closest to what's been typed so far
+pub async fn autocomplete_strain_names(ctx: Context<'_>, searching: &str) -> Vec<String> {
+    let searching = searching.trim();
     if searching.is_empty() {
-        return flavors.clone();
+        return Vec::new();
     }
 
-    flavors
-        .clone()
-        .into_iter()
-        .filter(|flavor| flavor.to_lowercase().starts_with(&searching.to_lowercase()))
-        .collect()
+    sqlx::query_scalar!(
+        r#"
+        SELECT name
+        FROM public.strains
+        WHERE name ILIKE ('%' || $1 || '%') OR name % $1
+        GROUP BY name
+        ORDER BY
+            BOOL_OR(name ILIKE ($1 || '%')) DESC,
+            MAX(SIMILARITY(name, $1)) DESC,
+            name ASC
+        LIMIT 25;"#,
+        searching
+    )
+    .fetch_all(&ctx.data().pool)
+    .await
+    .unwrap_or_default()
 }
 
-async fn autocomplete_effects(ctx: Context<'_>, searching: &str) -> Vec<String> {
-    let effects = EFFECTS
-        .get_or_init(|| async {
-            sqlx::query_scalar!(
-                "SELECT DISTINCT effect
-                FROM public.unique_effects
-                ORDER BY effect ASC
-                LIMIT 100;"
-            )
-            .fetch_all(&ctx.data().pool)
-            .await
-            .unwrap_or_default()
-        })
-        .await;
+/// Most choices Discord shows for an autocomplete
+const AUTOCOMPLETE_LIMIT: usize = 25;
+/// Longest choice Discord accepts
+const CHOICE_MAX_LENGTH: usize = 100;
 
-    if searching.is_empty() {
-        return effects.clone();
+/// How well `value` matches what's been typed, lower is better. `None` if it doesn't match
+///
+/// Both must already be lowercase
+fn match_rank(value: &str, partial: &str) -> Option<u8> {
+    if partial.is_empty() || value == partial {
+        return Some(0);
     }
-    effects
-        .clone()
-        .into_iter()
-        .filter(|effect| effect.to_lowercase().starts_with(&searching.to_lowercase()))
-        .collect()
+    if value.starts_with(partial) {
+        return Some(1);
+    }
+    // e.g. "mouth" for "Dry Mouth"
+    if value
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(partial))
+    {
+        return Some(2);
+    }
+    if value.contains(partial) {
+        return Some(3);
+    }
+
+    // Every typed character in order, allowing gaps, e.g. "slpy" for "Sleepy"
+    let mut chars = value.chars();
+    partial
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .all(|c| chars.any(|v| v == c))
+        .then_some(4)
 }
 
-async fn autocomplete_ailments(ctx: Context<'_>, searching: &str) -> Vec<String> {
-    let ailments = AILMENTS
-        .get_or_init(|| async {
-            sqlx::query_scalar!(
-                "SELECT DISTINCT ailment
-                FROM public.unique_ailments
-                ORDER BY ailment ASC
-                LIMIT 100;"
-            )
-            .fetch_all(&ctx.data().pool)
-            .await
-            .unwrap_or_default()
+/// Completes the last entry of a comma separated list, keeping the ones before it.
+///
+/// Closest matches come first, ties keep the order of `values`
+fn autocomplete_list(values: &[String], searching: &str) -> Vec<String> {
+    let (chosen, partial) = match searching.rsplit_once(',') {
+        Some((chosen, partial)) => (Some(chosen.trim()), partial.trim()),
+        None => (None, searching.trim()),
+    };
+    let already_chosen = parse_list(chosen);
+    let partial = strip_label(partial).to_lowercase();
+
+    let mut matches: Vec<(u8, &String)> = values
+        .iter()
+        .filter_map(|value| {
+            let lowercase = strip_label(value).to_lowercase();
+            if already_chosen.contains(&lowercase) {
+                return None;
+            }
+            match_rank(&lowercase, &partial).map(|rank| (rank, value))
         })
-        .await;
+        .collect();
+    // Stable, so values with the same rank stay alphabetical
+    matches.sort_by_key(|(rank, _)| *rank);
 
-    if searching.is_empty() {
-        return ailments.clone();
-    }
-    ailments
-        .clone()
+    matches
         .into_iter()
-        .filter(|ailment| {
-            ailment
-                .to_lowercase()
-                .starts_with(&searching.to_lowercase())
+        .map(|(_, value)| match chosen {
+            Some(chosen) => format!("{chosen}, {value}"),
+            None => value.clone(),
         })
+        .filter(|choice| choice.chars().count() <= CHOICE_MAX_LENGTH)
+        .take(AUTOCOMPLETE_LIMIT)
+        .collect()
+}
+
+pub async fn autocomplete_flavors(ctx: Context<'_>, searching: &str) -> Vec<String> {
+    let flavors = FLAVORS.get(&ctx.data().pool).await.unwrap_or_default();
+    autocomplete_list(&flavors, searching)
+}
+
+/// Effects matching `polarity` (all of them if `None`), labelled with their polarity
+async fn effect_labels(ctx: Context<'_>, polarity: Option<bool>) -> Vec<String> {
+    EFFECTS
+        .get(&ctx.data().pool)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .filter(|effect| polarity.is_none_or(|positive| effect.is_positive == positive))
+        .map(Effect::label)
         .collect()
 }
+
+pub async fn autocomplete_effects(ctx: Context<'_>, searching: &str) -> Vec<String> {
+    autocomplete_list(&effect_labels(ctx, None).await, searching)
+}
+
+pub async fn autocomplete_positive_effects(ctx: Context<'_>, searching: &str) -> Vec<String> {
+    autocomplete_list(&effect_labels(ctx, Some(true)).await, searching)
+}
+
+pub async fn autocomplete_negative_effects(ctx: Context<'_>, searching: &str) -> Vec<String> {
+    autocomplete_list(&effect_labels(ctx, Some(false)).await, searching)
+}
+
+pub async fn autocomplete_ailments(ctx: Context<'_>, searching: &str) -> Vec<String> {
+    let ailments = AILMENTS.get(&ctx.data().pool).await.unwrap_or_default();
+    autocomplete_list(&ailments, searching)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn strip_label_removes_polarity() {
+        assert_eq!(strip_label("🔺 Relaxed"), "Relaxed");
+        assert_eq!(strip_label(" 🔻 Dry Mouth "), "Dry Mouth");
+        assert_eq!(strip_label("Sleepy"), "Sleepy");
+    }
+
+    #[test]
+    fn parse_list_lowercases_and_dedups() {
+        assert_eq!(
+            parse_list(Some("Relaxed, sleepy,, RELAXED ,🔻 Dry Mouth")),
+            ["relaxed", "sleepy", "dry mouth"]
+        );
+        assert!(parse_list(Some(" , ")).is_empty());
+        assert!(parse_list(None).is_empty());
+    }
+
+    #[test]
+    fn match_rank_orders_match_quality() {
+        assert_eq!(match_rank("sleepy", ""), Some(0));
+        assert_eq!(match_rank("sleepy", "sleepy"), Some(0));
+        assert_eq!(match_rank("sleepy", "sle"), Some(1));
+        assert_eq!(match_rank("dry mouth", "mou"), Some(2));
+        assert_eq!(match_rank("blueberry", "berry"), Some(3));
+        assert_eq!(match_rank("sleepy", "slpy"), Some(4));
+        assert_eq!(match_rank("sleepy", "happy"), None);
+    }
+
+    #[test]
+    fn autocomplete_list_sorts_by_rank() {
+        let flavors = values(&["Berry", "Blueberry", "Strawberry", "Sweet", "Sweet Berry"]);
+
+        assert_eq!(
+            autocomplete_list(&flavors, "berry"),
+            ["Berry", "Sweet Berry", "Blueberry", "Strawberry"]
+        );
+        assert_eq!(autocomplete_list(&flavors, "").len(), flavors.len());
+    }
+
+    #[test]
+    fn autocomplete_list_keeps_chosen_values() {
+        let flavors = values(&["Berry", "Blueberry", "Sweet", "Sweet Berry"]);
+
+        assert_eq!(
+            autocomplete_list(&flavors, "Sweet, ber"),
+            ["Sweet, Berry", "Sweet, Sweet Berry", "Sweet, Blueberry"]
+        );
+    }
+
+    #[test]
+    fn autocomplete_list_ignores_labels() {
+        let effects = values(&["🔺 Relaxed", "🔻 Dry Mouth", "🔻 Paranoid"]);
+
+        assert_eq!(autocomplete_list(&effects, "mouth"), ["🔻 Dry Mouth"]);
+        assert_eq!(
+            autocomplete_list(&effects, "🔺 Relaxed, 🔻 par"),
+            ["🔺 Relaxed, 🔻 Paranoid"]
+        );
+    }
+
+    #[test]
+    fn autocomplete_list_respects_discord_limits() {
+        let many: Vec<String> = (0..40).map(|i| format!("Flavor {i:02}")).collect();
+        assert_eq!(autocomplete_list(&many, "flavor").len(), AUTOCOMPLETE_LIMIT);
+
+        let chosen = "a".repeat(CHOICE_MAX_LENGTH - 5);
+        let flavors = values(&["Tea", "Lemon Peel"]);
+        assert_eq!(
+            autocomplete_list(&flavors, &format!("{chosen}, ")),
+            [format!("{chosen}, Tea")]
+        );
+    }
+}
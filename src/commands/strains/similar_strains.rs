@@ -0,0 +1,165 @@
+use poise::{
+    serenity_prelude::{Color, CreateEmbed},
+    CreateReply,
+};
+use sqlx::PgPool;
+
+use crate::{
+    error_reply,
+    types::{CommandResult, Context},
+};
+
+/// Strains listed by `/strain similar` and the "Similar" button
+pub const SIMILAR_LIMIT: i64 = 10;
+
+pub struct SimilarStrain {
+    pub id: i64,
+    pub name: String,
+    /// Weighted Jaccard similarity, from 0 to 1
+    pub score: f64,
+}
+
+/// Strains sharing the most effects, flavors and ailments with strain `id`.
+///
+/// Each category is scored by Jaccard similarity (shared / combined), then weighted
+/// so effects count the most, followed by flavors and ailments. Categories the
+/// strain has no data for are left out of the weighting.
+pub async fn similar_strains(
+    pool: &PgPool,
+    id: i64,
+    limit: i64,
+) -> Result<Vec<SimilarStrain>, sqlx::Error> {
+    sqlx::query_as!(
+        SimilarStrain,
+        r#"
+        WITH
+            target AS (
+                SELECT 'effect' AS kind, effect_id AS tag, 0.5::FLOAT8 AS weight
+                FROM public.strain_effects
+                WHERE strain_id = $1
+                UNION ALL
+                SELECT 'flavor', flavor_id, 0.3
+                FROM public.strain_flavors
+                WHERE strain_id = $1
+                UNION ALL
+                SELECT 'ailment', ailment_id, 0.2
+                FROM public.strain_ailments
+                WHERE strain_id = $1
+            ),
+            -- Only strains sharing at least one tag with the target can score
+            candidates AS (
+                SELECT se.strain_id
+                FROM public.strain_effects se
+                JOIN target t ON t.kind = 'effect' AND t.tag = se.effect_id
+                UNION
+                SELECT sf.strain_id
+                FROM public.strain_flavors sf
+                JOIN target t ON t.kind = 'flavor' AND t.tag = sf.flavor_id
+                UNION
+                SELECT sa.strain_id
+                FROM public.strain_ailments sa
+                JOIN target t ON t.kind = 'ailment' AND t.tag = sa.ailment_id
+            ),
+            tags AS (
+                SELECT strain_id, 'effect' AS kind, effect_id AS tag
+                FROM public.strain_effects
+                WHERE strain_id IN (SELECT strain_id FROM candidates) AND strain_id <> $1
+                UNION ALL
+                SELECT strain_id, 'flavor', flavor_id
+                FROM public.strain_flavors
+                WHERE strain_id IN (SELECT strain_id FROM candidates) AND strain_id <> $1
+                UNION ALL
+                SELECT strain_id, 'ailment', ailment_id
+                FROM public.strain_ailments
+                WHERE strain_id IN (SELECT strain_id FROM candidates) AND strain_id <> $1
+            ),
+            target_sizes AS (
+                SELECT kind, MAX(weight) AS weight, COUNT(*) AS size
+                FROM target
+                GROUP BY kind
+            ),
+            shared AS (
+                SELECT t.strain_id, t.kind, COUNT(*) AS shared
+                FROM tags t
+                JOIN target USING (kind, tag)
+                GROUP BY t.strain_id, t.kind
+            ),
+            sizes AS (
+                SELECT strain_id, kind, COUNT(*) AS size
+                FROM tags
+                GROUP BY strain_id, kind
+            )
+        SELECT
+            s.id,
+            s.name,
+            SUM(
+                ts.weight * sh.shared::FLOAT8 / (ts.size + sz.size - sh.shared)
+            ) / (SELECT SUM(weight) FROM target_sizes) AS "score!"
+        FROM shared sh
+        JOIN sizes sz USING (strain_id, kind)
+        JOIN target_sizes ts USING (kind)
+        JOIN public.strains s ON s.id = sh.strain_id
+        GROUP BY s.id, s.name
+        ORDER BY "score!" DESC, s.id ASC
+        LIMIT $2;
+        "#,
+        id,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+}
+
+pub fn similar_embed(name: &str, similar: &[SimilarStrain]) -> CreateEmbed {
+    let embed = CreateEmbed::default()
+        .title(format!("Strains similar to {name}"))
+        .color(Color::PURPLE);
+
+    if similar.is_empty() {
+        return embed.description("No similar strains found");
+    }
+
+    let mut description = String::new();
+    for strain in similar {
+        description.push_str(&format!(
+            "- `{}`: **{}** ({:.0}% match)",
+            strain.id,
+            strain.name,
+            strain.score * 100.0
+        ));
+        description.push('\n');
+    }
+    embed.description(description)
+}
+
+/// Finds strains with similar effects, flavors and ailments
+#[poise::command(
+    slash_command,
+    rename = "similar",
+    required_bot_permissions = "SEND_MESSAGES",
+    nsfw_only = true
+)]
+pub async fn strain_similar(
+    ctx: Context<'_>,
+    #[description = "ID of the strain"] id: i64,
+) -> CommandResult {
+    let pool = &ctx.data().pool;
+
+    let name = sqlx::query_scalar!("SELECT name FROM public.strains WHERE id = $1;", id)
+        .fetch_optional(pool)
+        .await?;
+
+    let Some(name) = name else {
+        ctx.send(error_reply(
+            "Strain Not Found",
+            Some(&format!("Couldn't find strain with id: `{}`", id)),
+        ))
+        .await?;
+        return Ok(());
+    };
+
+    let similar = similar_strains(pool, id, SIMILAR_LIMIT).await?;
+    ctx.send(CreateReply::default().embed(similar_embed(&name, &similar)))
+        .await?;
+    Ok(())
+}
@@ -1,12 +1,18 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
 use poise::{
     serenity_prelude::{Color, CreateActionRow, CreateButton, CreateEmbed, ReactionType},
     CreateReply,
 };
 use sqlx::PgPool;
 
-use crate::types::{CommandResult, Context};
+use crate::{
+    cache::RefreshCache,
+    types::{CommandResult, Context},
+};
 
-struct StrainTotals {
+pub struct StrainTotals {
     total: i64,
     hybrid: i64,
     indica: i64,
@@ -19,9 +25,12 @@ impl StrainTotals {
     }
 }
 
-static STRAIN_COUNTS: tokio::sync::OnceCell<StrainTotals> = tokio::sync::OnceCell::const_new();
+pub static STRAIN_COUNTS: RefreshCache<StrainTotals> =
+    RefreshCache::new("strain counts", Duration::from_secs(60 * 60), |pool| {
+        Box::pin(async move { strain_counts(&pool).await })
+    });
 
-async fn strain_counts(pool: &PgPool) -> StrainTotals {
+async fn strain_counts(pool: &PgPool) -> Result<StrainTotals, sqlx::Error> {
     let totals = sqlx::query!(
         r#"
         SELECT
@@ -47,15 +56,14 @@ async fn strain_counts(pool: &PgPool) -> StrainTotals {
     "#
     )
     .fetch_one(pool)
-    .await
-    .unwrap();
+    .await?;
 
-    StrainTotals {
+    Ok(StrainTotals {
         total: totals.total_strains,
         hybrid: totals.total_hybrid,
         indica: totals.total_indica,
         sativa: totals.total_sativa,
-    }
+    })
 }
 
 #[poise::command(
@@ -65,8 +73,9 @@ async fn strain_counts(pool: &PgPool) -> StrainTotals {
 )]
 pub async fn about(ctx: Context<'_>) -> CommandResult {
     let strain_counts = STRAIN_COUNTS
-        .get_or_init(|| async { strain_counts(&ctx.data().pool).await })
-        .await;
+        .get(&ctx.data().pool)
+        .await
+        .ok_or_else(|| anyhow!("Failed to fetch strain counts"))?;
 
     let server_count = ctx.cache().guild_count();
 
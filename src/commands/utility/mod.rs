@@ -1,3 +1,4 @@
 pub mod about;
 pub mod help;
 pub mod ping;
+pub mod privacy;
@@ -0,0 +1,215 @@
+use std::time::Duration;
+
+use poise::{
+    serenity_prelude::{
+        self as serenity, ButtonStyle, Color, CreateActionRow, CreateAttachment, CreateButton,
+        CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage,
+        ReactionType,
+    },
+    CreateReply,
+};
+use tracing::error;
+
+use crate::{
+    error_reply,
+    types::{CommandResult, Context},
+    user_data,
+};
+
+/// Manage the data BudBuddy stores about you
+#[poise::command(
+    slash_command,
+    category = "Utility",
+    subcommands(
+        "privacy_export",
+        "privacy_delete",
+        "privacy_opt_out",
+        "privacy_opt_in"
+    ),
+    subcommand_required
+)]
+pub async fn privacy(_ctx: Context<'_>) -> CommandResult {
+    // This command cannot be called by the client because of Discord's subcommand system.
+    // See https://discord.com/developers/docs/interactions/application-commands#subcommands-and-subcommand-groups
+    Ok(())
+}
+
+/// Get a copy of everything stored about you, sent to your DMs
+#[poise::command(slash_command, rename = "export", user_cooldown = 60)]
+pub async fn privacy_export(ctx: Context<'_>) -> CommandResult {
+    ctx.defer_ephemeral().await?;
+
+    let user = ctx.author();
+    let export = user_data::export(&ctx.data().pool, &user.id).await?;
+
+    let message = CreateMessage::new()
+        .embed(
+            CreateEmbed::new()
+                .title("Your BudBuddy data")
+                .description("Everything BudBuddy stores about you is attached")
+                .color(Color::PURPLE),
+        )
+        .add_file(CreateAttachment::bytes(
+            export.into_bytes(),
+            format!("budbuddy-{}.json", user.id),
+        ));
+
+    if user.direct_message(ctx, message).await.is_err() {
+        ctx.send(error_reply(
+            "Couldn't DM you",
+            Some("Allow direct messages from server members, then try again"),
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .title("Data export sent")
+                    .description("Check your DMs")
+                    .color(Color::PURPLE),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Asks the user to confirm with buttons, then runs `action` and shows the embed it returns.
+///
+/// Nothing happens if they cancel or don't answer within 30 seconds
+async fn confirm<F, Fut>(
+    ctx: Context<'_>,
+    prompt: CreateEmbed,
+    confirm_label: &str,
+    action: F,
+) -> CommandResult
+where
+    F: FnOnce() -> Fut,
+    Fut: std::future::Future<Output = CreateEmbed>,
+{
+    let cancel_id = format!("{}-privacy-cancel", ctx.id());
+    let confirm_id = format!("{}-privacy-confirm", ctx.id());
+
+    let buttons = vec![
+        CreateButton::new(&cancel_id)
+            .style(ButtonStyle::Primary)
+            .label("Cancel")
+            .emoji(ReactionType::Unicode("✖️".into())),
+        CreateButton::new(&confirm_id)
+            .label(confirm_label)
+            .style(ButtonStyle::Danger)
+            .emoji(ReactionType::Unicode("⚠️".into())),
+    ];
+
+    ctx.send(
+        CreateReply::default()
+            .embed(prompt)
+            .components(vec![CreateActionRow::Buttons(buttons)])
+            .ephemeral(true),
+    )
+    .await?;
+
+    if let Some(button_interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .custom_ids(vec![cancel_id.clone(), confirm_id.clone()])
+        .timeout(Duration::from_secs(30))
+        .await
+    {
+        let embed = if button_interaction.data.custom_id.eq(&confirm_id) {
+            action().await
+        } else {
+            CreateEmbed::default().title("Canceled")
+        };
+
+        button_interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::default()
+                        .embed(embed)
+                        .components(vec![]),
+                ),
+            )
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Delete everything stored about you
+///
+/// If you're blacklisted, the blacklist is kept
+#[poise::command(slash_command, rename = "delete", user_cooldown = 30)]
+pub async fn privacy_delete(ctx: Context<'_>) -> CommandResult {
+    let prompt = CreateEmbed::default().title("Are you sure?").description(
+        "This permanently deletes your puffs, streaks, achievements and history. This can't be undone",
+    );
+
+    confirm(ctx, prompt, "Delete", || async {
+        match user_data::delete_user_data(&ctx.data().pool, &ctx.author().id).await {
+            Ok(()) => CreateEmbed::default()
+                .title("Your data was deleted")
+                .color(Color::PURPLE),
+            Err(error) => {
+                error!("Failed to delete data for {}: {:?}", ctx.author().id, error);
+                CreateEmbed::default()
+                    .title("Failed to delete your data")
+                    .color(Color::RED)
+            }
+        }
+    })
+    .await
+}
+
+/// Delete everything stored about you, and stop tracking puffs for you
+#[poise::command(slash_command, rename = "opt-out", user_cooldown = 30)]
+pub async fn privacy_opt_out(ctx: Context<'_>) -> CommandResult {
+    let prompt = CreateEmbed::default().title("Are you sure?").description(
+        "This permanently deletes your puffs, streaks, achievements and history, and stops BudBuddy from storing any more. Use `/privacy opt-in` to change your mind later",
+    );
+
+    confirm(ctx, prompt, "Opt out", || async {
+        match user_data::opt_out(&ctx.data().pool, &ctx.author().id).await {
+            Ok(()) => CreateEmbed::default()
+                .title("You've opted out")
+                .description("Your data was deleted, and puffs won't be tracked for you")
+                .color(Color::PURPLE),
+            Err(error) => {
+                error!("Failed to opt out for {}: {:?}", ctx.author().id, error);
+                CreateEmbed::default()
+                    .title("Failed to opt out")
+                    .color(Color::RED)
+            }
+        }
+    })
+    .await
+}
+
+/// Start tracking puffs for you again after opting out
+#[poise::command(slash_command, rename = "opt-in", user_cooldown = 30)]
+pub async fn privacy_opt_in(ctx: Context<'_>) -> CommandResult {
+    if !user_data::opt_in(&ctx.data().pool, &ctx.author().id).await? {
+        ctx.send(error_reply(
+            "Not opted out",
+            Some("Puffs are already being tracked for you"),
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::new()
+                    .title("You've opted back in")
+                    .description("Use `/puff take` to start tracking puffs again")
+                    .color(Color::PURPLE),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
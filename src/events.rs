@@ -0,0 +1,116 @@
+//! Gateway events, dispatched through poise's `event_handler`
+
+use poise::serenity_prelude::{self as serenity, FullEvent, GuildId};
+use sqlx::{types::BigDecimal, PgPool};
+use tracing::{debug, info};
+
+use crate::types::{CommandError, Data};
+
+pub async fn event_handler(
+    ctx: &serenity::Context,
+    event: &FullEvent,
+    _framework: poise::FrameworkContext<'_, Data, CommandError>,
+    data: &Data,
+) -> Result<(), CommandError> {
+    match event {
+        // Sent for every guild on startup too, not only when joining
+        FullEvent::GuildCreate { guild, .. } => {
+            // Only leave when the blacklist was actually read, whatever the failure policy
+            if data.leave_blacklisted_guilds
+                && data
+                    .blacklist
+                    .is_guild_blacklisted(&data.pool, &guild.id)
+                    .await
+                    .unwrap_or(false)
+            {
+                info!("Leaving blacklisted guild {}", guild.id);
+                guild.id.leave(ctx).await?;
+                return Ok(());
+            }
+            guild_added(&data.pool, &guild.id).await?
+        }
+        // Catches guilds that removed the bot while it was offline
+        FullEvent::Ready { data_about_bot } => missed_removals(&data.pool, data_about_bot).await?,
+        // `unavailable` guilds are outages, the bot is still in them
+        FullEvent::GuildDelete { incomplete, .. } if !incomplete.unavailable => {
+            guild_removed(&data.pool, &incomplete.id).await?
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Cancels a pending purge if the bot is re-added during the grace period
+async fn guild_added(pool: &PgPool, guild_id: &GuildId) -> Result<(), sqlx::Error> {
+    let cancelled = sqlx::query!(
+        r#"
+        UPDATE discord.guilds
+        SET removed_at = NULL
+        WHERE guild_id = $1 AND removed_at IS NOT NULL;"#,
+        BigDecimal::from(guild_id.get())
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if cancelled > 0 {
+        debug!("Re-added to guild {}, cancelled its data purge", guild_id);
+    }
+    Ok(())
+}
+
+/// Marks the guild's data for purging, see `scheduler::prune_expired`
+async fn guild_removed(pool: &PgPool, guild_id: &GuildId) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE discord.guilds
+        SET removed_at = NOW()
+        WHERE guild_id = $1 AND removed_at IS NULL;"#,
+        BigDecimal::from(guild_id.get())
+    )
+    .execute(pool)
+    .await?;
+
+    info!("Removed from guild {}, scheduled its data purge", guild_id);
+    Ok(())
+}
+
+/// Marks the data of every guild the shard is no longer in for purging.
+///
+/// `ready.guilds` lists every guild of the shard, including unavailable ones
+async fn missed_removals(pool: &PgPool, ready: &serenity::Ready) -> Result<(), sqlx::Error> {
+    let guild_ids: Vec<BigDecimal> = ready
+        .guilds
+        .iter()
+        .map(|guild| BigDecimal::from(guild.id.get()))
+        .collect();
+    let (shard, shards) = ready
+        .shard
+        .map(|shard| (i64::from(shard.id.0), i64::from(shard.total)))
+        .unwrap_or((0, 1));
+
+    let removed = sqlx::query!(
+        r#"
+        UPDATE discord.guilds
+        SET removed_at = NOW()
+        WHERE
+            removed_at IS NULL
+            AND NOT (guild_id = ANY($1::NUMERIC[]))
+            -- Guilds of other shards are in their own Ready
+            AND MOD(TRUNC(guild_id / 4194304), $2::BIGINT) = $3::BIGINT;"#,
+        &guild_ids,
+        shards,
+        shard
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if removed > 0 {
+        info!(
+            "Removed from {} guilds while offline, scheduled their data purge",
+            removed
+        );
+    }
+    Ok(())
+}
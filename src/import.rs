@@ -0,0 +1,417 @@
+//! `budbuddy import <file>...`
+//!
+//! Loads strain datasets from CSV or JSON into `public.strains` and the
+//! effect, flavor and ailment join tables.
+//!
+//! JSON files must contain an array of [`StrainRecord`]s. CSV files use the same
+//! field names as headers, with list columns separated by commas inside the cell:
+//! ```csv
+//! name,subspecies,description,image_url,effects,negative_effects,flavors,ailments
+//! Blue Dream,Hybrid,"A sativa-dominant hybrid...",,"Relaxed, Happy","Dry Mouth","Blueberry, Sweet",Stress
+//! ```
+use std::{collections::HashMap, io::Read, path::Path, str::FromStr};
+
+use anyhow::{anyhow, bail, Context as _};
+use serde::{Deserialize, Deserializer};
+use sqlx::{PgPool, Postgres, Transaction};
+use tracing::{info, instrument, warn};
+
+use crate::commands::strains::search_strains::Subspecies;
+
+#[derive(Debug, Deserialize)]
+pub struct StrainRecord {
+    pub name: String,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub subspecies: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub description: Option<String>,
+    #[serde(default, deserialize_with = "empty_as_none")]
+    pub image_url: Option<String>,
+    /// Positive effects, unless the effect already exists as a negative one
+    #[serde(default, deserialize_with = "list")]
+    pub effects: Vec<String>,
+    #[serde(default, deserialize_with = "list")]
+    pub negative_effects: Vec<String>,
+    #[serde(default, deserialize_with = "list")]
+    pub flavors: Vec<String>,
+    #[serde(default, deserialize_with = "list")]
+    pub ailments: Vec<String>,
+}
+
+/// Accepts either a JSON array or a comma separated string (for CSV cells)
+fn list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List {
+        Many(Vec<String>),
+        One(String),
+    }
+
+    let values = match List::deserialize(deserializer)? {
+        List::Many(values) => values,
+        List::One(value) => value.split(',').map(str::to_string).collect(),
+    };
+
+    Ok(values
+        .iter()
+        .map(|v| v.trim())
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+fn empty_as_none<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<String>, D::Error> {
+    let value = Option::<String>::deserialize(deserializer)?;
+    Ok(value
+        .map(|v| v.trim().to_string())
+        .filter(|v| !v.is_empty()))
+}
+
+/// Entry point for the `import` subcommand
+pub async fn run(pool: &PgPool, paths: &[String]) -> Result<(), anyhow::Error> {
+    if paths.is_empty() {
+        bail!("Usage: budbuddy import <file.csv|file.json>...");
+    }
+
+    for path in paths {
+        let records = read_records(Path::new(path))?;
+        info!("Importing {} strains from {path}", records.len());
+
+        let imported = import_records(pool, records).await?;
+        info!("Imported {imported} strains from {path}");
+    }
+    Ok(())
+}
+
+fn read_records(path: &Path) -> Result<Vec<StrainRecord>, anyhow::Error> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase);
+
+    match extension.as_deref() {
+        Some("json") => {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            parse_json(std::io::BufReader::new(file))
+                .with_context(|| format!("Failed to parse {}", path.display()))
+        }
+        Some("csv") => {
+            let file = std::fs::File::open(path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            parse_csv(file).with_context(|| format!("Failed to parse {}", path.display()))
+        }
+        _ => Err(anyhow!(
+            "Unsupported file type: {} (expected .csv or .json)",
+            path.display()
+        )),
+    }
+}
+
+fn parse_json(reader: impl Read) -> Result<Vec<StrainRecord>, serde_json::Error> {
+    serde_json::from_reader(reader)
+}
+
+fn parse_csv(reader: impl Read) -> Result<Vec<StrainRecord>, csv::Error> {
+    csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .from_reader(reader)
+        .deserialize()
+        .collect()
+}
+
+/// Case-insensitive lookup of existing vocabulary, e.g. `unique_effects`
+type Vocabulary = HashMap<String, i32>;
+
+#[instrument(skip_all)]
+async fn import_records(pool: &PgPool, records: Vec<StrainRecord>) -> Result<u64, anyhow::Error> {
+    let mut tx = pool.begin().await?;
+
+    let mut effects: Vocabulary = sqlx::query!("SELECT id, effect FROM public.unique_effects;")
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|r| (r.effect.to_lowercase(), r.id))
+        .collect();
+    let mut flavors: Vocabulary = sqlx::query!("SELECT id, flavor FROM public.unique_flavors;")
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|r| (r.flavor.to_lowercase(), r.id))
+        .collect();
+    let mut ailments: Vocabulary = sqlx::query!("SELECT id, ailment FROM public.unique_ailments;")
+        .fetch_all(&mut *tx)
+        .await?
+        .into_iter()
+        .map(|r| (r.ailment.to_lowercase(), r.id))
+        .collect();
+
+    let mut imported = 0;
+    for record in records {
+        let name = record.name.trim();
+        if name.is_empty() {
+            warn!("Skipping strain without a name");
+            continue;
+        }
+
+        let subspecies = match record.subspecies.as_deref().map(Subspecies::from_str) {
+            Some(Ok(s)) => Some(s),
+            Some(Err(_)) => {
+                warn!(
+                    "Unknown subspecies {:?} for {name}",
+                    record.subspecies.as_deref().unwrap_or_default()
+                );
+                None
+            }
+            None => None,
+        };
+
+        let strain_id = upsert_strain(&mut tx, name, &record, subspecies).await?;
+
+        for effect in &record.effects {
+            let effect_id = effect_id(&mut tx, &mut effects, effect, true).await?;
+            sqlx::query!(
+                "INSERT INTO public.strain_effects (strain_id, effect_id) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
+                strain_id,
+                effect_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        for effect in &record.negative_effects {
+            let effect_id = effect_id(&mut tx, &mut effects, effect, false).await?;
+            sqlx::query!(
+                "INSERT INTO public.strain_effects (strain_id, effect_id) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
+                strain_id,
+                effect_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        for flavor in &record.flavors {
+            let flavor_id = flavor_id(&mut tx, &mut flavors, flavor).await?;
+            sqlx::query!(
+                "INSERT INTO public.strain_flavors (strain_id, flavor_id) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
+                strain_id,
+                flavor_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+        for ailment in &record.ailments {
+            let ailment_id = ailment_id(&mut tx, &mut ailments, ailment).await?;
+            sqlx::query!(
+                "INSERT INTO public.strain_ailments (strain_id, ailment_id) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
+                strain_id,
+                ailment_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        imported += 1;
+    }
+
+    tx.commit().await?;
+    Ok(imported)
+}
+
+/// Updates the strain with the same (case-insensitive) name, or inserts a new one
+async fn upsert_strain(
+    tx: &mut Transaction<'_, Postgres>,
+    name: &str,
+    record: &StrainRecord,
+    subspecies: Option<Subspecies>,
+) -> Result<i64, sqlx::Error> {
+    let existing = sqlx::query_scalar!(
+        r#"
+        UPDATE public.strains
+        SET
+            description = COALESCE($2, description),
+            subspecies = COALESCE($3, subspecies),
+            image_url = COALESCE($4, image_url)
+        WHERE
+            id = (
+                SELECT id
+                FROM public.strains
+                WHERE LOWER(name) = LOWER($1)
+                ORDER BY id ASC
+                LIMIT 1
+            )
+        RETURNING id;"#,
+        name,
+        record.description,
+        subspecies as _,
+        record.image_url
+    )
+    .fetch_optional(&mut **tx)
+    .await?;
+
+    if let Some(id) = existing {
+        return Ok(id);
+    }
+
+    sqlx::query_scalar!(
+        r#"
+        INSERT INTO public.strains (name, description, subspecies, image_url)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id;"#,
+        name,
+        record.description,
+        subspecies as _,
+        record.image_url
+    )
+    .fetch_one(&mut **tx)
+    .await
+}
+
+/// Trims and collapses whitespace, title-casing values that are entirely lower case.
+///
+/// Anything with a capital letter is kept as written, so acronyms like `PTSD` survive
+fn normalise(value: &str) -> String {
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    if value.chars().any(char::is_uppercase) {
+        return value;
+    }
+
+    value
+        .split(' ')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+async fn effect_id(
+    tx: &mut Transaction<'_, Postgres>,
+    effects: &mut Vocabulary,
+    effect: &str,
+    is_positive: bool,
+) -> Result<i32, sqlx::Error> {
+    let effect = normalise(effect);
+    if let Some(id) = effects.get(&effect.to_lowercase()) {
+        return Ok(*id);
+    }
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO public.unique_effects (effect, is_positive) VALUES ($1, $2) RETURNING id;",
+        effect,
+        is_positive
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    info!("Added new effect: {effect}");
+
+    effects.insert(effect.to_lowercase(), id);
+    Ok(id)
+}
+
+async fn flavor_id(
+    tx: &mut Transaction<'_, Postgres>,
+    flavors: &mut Vocabulary,
+    flavor: &str,
+) -> Result<i32, sqlx::Error> {
+    let flavor = normalise(flavor);
+    if let Some(id) = flavors.get(&flavor.to_lowercase()) {
+        return Ok(*id);
+    }
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO public.unique_flavors (flavor) VALUES ($1) RETURNING id;",
+        flavor
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    info!("Added new flavor: {flavor}");
+
+    flavors.insert(flavor.to_lowercase(), id);
+    Ok(id)
+}
+
+async fn ailment_id(
+    tx: &mut Transaction<'_, Postgres>,
+    ailments: &mut Vocabulary,
+    ailment: &str,
+) -> Result<i32, sqlx::Error> {
+    let ailment = normalise(ailment);
+    if let Some(id) = ailments.get(&ailment.to_lowercase()) {
+        return Ok(*id);
+    }
+
+    let id = sqlx::query_scalar!(
+        "INSERT INTO public.unique_ailments (ailment) VALUES ($1) RETURNING id;",
+        ailment
+    )
+    .fetch_one(&mut **tx)
+    .await?;
+    info!("Added new ailment: {ailment}");
+
+    ailments.insert(ailment.to_lowercase(), id);
+    Ok(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalise_title_cases_lowercase_values() {
+        assert_eq!(normalise("dry mouth"), "Dry Mouth");
+        assert_eq!(normalise("  blue   dream "), "Blue Dream");
+    }
+
+    #[test]
+    fn normalise_keeps_capitalised_values() {
+        assert_eq!(normalise("PTSD"), "PTSD");
+        assert_eq!(normalise("ADD/ADHD"), "ADD/ADHD");
+        assert_eq!(normalise("Lack of Appetite"), "Lack of Appetite");
+        assert_eq!(normalise("chronic PAIN"), "chronic PAIN");
+    }
+
+    #[test]
+    fn parses_csv_lists() {
+        let csv = "\
+name,subspecies,description,image_url,effects,negative_effects,flavors,ailments
+Blue Dream,Hybrid,,,\"Relaxed, Happy,\",Dry Mouth,\" Blueberry ,Sweet\",
+";
+        let records = parse_csv(csv.as_bytes()).unwrap();
+
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.name, "Blue Dream");
+        assert_eq!(record.subspecies.as_deref(), Some("Hybrid"));
+        assert_eq!(record.description, None);
+        assert_eq!(record.image_url, None);
+        assert_eq!(record.effects, ["Relaxed", "Happy"]);
+        assert_eq!(record.negative_effects, ["Dry Mouth"]);
+        assert_eq!(record.flavors, ["Blueberry", "Sweet"]);
+        assert!(record.ailments.is_empty());
+    }
+
+    #[test]
+    fn parses_json_lists() {
+        let json = r#"[
+            {
+                "name": "Blue Dream",
+                "description": "  ",
+                "effects": ["Relaxed", " Happy ", ""],
+                "flavors": "Blueberry, Sweet"
+            }
+        ]"#;
+        let records = parse_json(json.as_bytes()).unwrap();
+
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(record.subspecies, None);
+        assert_eq!(record.description, None);
+        assert_eq!(record.effects, ["Relaxed", "Happy"]);
+        assert_eq!(record.flavors, ["Blueberry", "Sweet"]);
+        assert!(record.negative_effects.is_empty());
+        assert!(record.ailments.is_empty());
+    }
+}
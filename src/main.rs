@@ -1,20 +1,27 @@
 use anyhow::anyhow;
-use sqlx::PgPool;
-use std::{env::VarError, process::exit};
+use std::{env::VarError, process::exit, time::Duration};
 use tracing_subscriber::EnvFilter;
 
 use poise::{
     serenity_prelude::{
         self as serenity, Color, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedAuthor,
-        CreateMessage, ReactionType, User,
+        CreateMessage, ReactionType,
     },
     CreateReply, FrameworkError, FrameworkOptions,
 };
 use tracing::{error, info, instrument, warn};
-use types::{CommandError, Context, Data};
+use types::{CheckError, CommandError, Context, Data};
 
+pub mod blacklist;
+pub mod cache;
+pub mod chart;
 pub mod commands;
+pub mod events;
+pub mod import;
+pub mod pagination;
+pub mod scheduler;
 pub mod types;
+pub mod user_data;
 
 #[tokio::main]
 #[instrument]
@@ -30,9 +37,14 @@ async fn main() -> Result<(), anyhow::Error> {
 
     let bot_data = Data::new().await;
 
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|arg| arg == "import") {
+        return import::run(&bot_data.pool, &args[1..]).await;
+    }
+
     let token = unwrap_env_var("DISCORD_TOKEN");
 
-    let _guild_id = match unwrap_env_var("GUILD_ID").parse() {
+    let guild_id = match unwrap_env_var("GUILD_ID").parse() {
         Ok(v) => serenity::GuildId::new(v),
         Err(error) => {
             error!(?error);
@@ -48,9 +60,14 @@ async fn main() -> Result<(), anyhow::Error> {
         commands::utility::help::help(),
         commands::utility::about::about(),
         commands::utility::ping::ping(),
+        commands::utility::privacy::privacy(),
         commands::strains::search_strains::search(),
         commands::strains::fetch_strain::strain(),
+        commands::strains::compare_strains::compare(),
         commands::strains::puff::puff(),
+        commands::admin::blacklist::admin(),
+        commands::admin::cache::clear_caches(),
+        commands::admin::config::config(),
         commands::admin::register::register(),
     ];
 
@@ -58,6 +75,9 @@ async fn main() -> Result<(), anyhow::Error> {
         commands,
         command_check: Some(|ctx| Box::pin(global_command_check(ctx))),
         on_error: |error| Box::pin(global_error_handler(error)),
+        event_handler: |ctx, event, framework, data| {
+            Box::pin(events::event_handler(ctx, event, framework, data))
+        },
         ..Default::default()
     };
 
@@ -66,9 +86,19 @@ async fn main() -> Result<(), anyhow::Error> {
         .setup(move |ctx, ready, framework| {
             Box::pin(async move {
                 info!("Registering commands...");
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                let (dev_commands, global_commands): (Vec<_>, Vec<_>) = framework
+                    .options()
+                    .commands
+                    .iter()
+                    .partition(|command| DEV_GUILD_COMMANDS.contains(&command.name.as_str()));
+                serenity::Command::set_global_commands(ctx, application_commands(&global_commands))
+                    .await?;
+                guild_id
+                    .set_commands(ctx, application_commands(&dev_commands))
+                    .await?;
 
                 info!("Online on bot: {} ({})", ready.user.name, ready.user.id);
+                scheduler::start(ctx.http.clone(), bot_data.pool.clone());
                 Ok(bot_data)
             })
         })
@@ -83,6 +113,25 @@ async fn main() -> Result<(), anyhow::Error> {
     Ok(())
 }
 
+/// Owner only commands, only registered in the `GUILD_ID` guild so other servers never see them
+const DEV_GUILD_COMMANDS: &[&str] = &["admin", "clear-caches"];
+
+/// Slash and context menu commands to register, like `poise::builtins::create_application_commands`
+fn application_commands(
+    commands: &[&poise::Command<Data, CommandError>],
+) -> Vec<serenity::CreateCommand> {
+    commands
+        .iter()
+        .flat_map(|command| {
+            [
+                command.create_as_slash_command(),
+                command.create_as_context_menu_command(),
+            ]
+        })
+        .flatten()
+        .collect()
+}
+
 fn unwrap_env_var(name: &str) -> String {
     match std::env::var(name) {
         Ok(t) => t,
@@ -100,8 +149,8 @@ fn unwrap_env_var(name: &str) -> String {
 fn error_embed(title: &str, message: Option<&str>) -> CreateEmbed {
     let mut embed = CreateEmbed::default().color(Color::RED).title(title);
 
-    if message.is_some() {
-        embed = embed.description(message.unwrap());
+    if let Some(message) = message {
+        embed = embed.description(message);
     }
     embed
 }
@@ -112,33 +161,30 @@ fn error_reply(title: &str, message: Option<&str>) -> CreateReply {
     CreateReply::default().embed(embed).ephemeral(true)
 }
 
-async fn global_command_check(ctx: Context<'_>) -> Result<bool, CommandError> {
-    let user_is_blacklisted = is_user_blacklisted(&ctx.data().pool, ctx.author()).await;
-
-    if user_is_blacklisted {
-        return Err(anyhow!("User is blacklisted"));
-    }
-    Ok(!user_is_blacklisted)
+fn cooldown_reply(ctx: Context<'_>, remaining_cooldown: Duration) -> CreateReply {
+    error_reply(
+        &format!("/{} on cooldown", ctx.command().qualified_name),
+        Some(&format!(
+            "Please wait {:.1} seconds before trying again",
+            remaining_cooldown.as_secs_f32()
+        )),
+    )
 }
 
-async fn is_user_blacklisted(pool: &PgPool, user: &User) -> bool {
-    let id = sqlx::types::BigDecimal::from(user.id.get());
+async fn global_command_check(ctx: Context<'_>) -> Result<bool, CommandError> {
+    let pool = &ctx.data().pool;
+    let blacklist = &ctx.data().blacklist;
 
-    let is_user_blacklisted = sqlx::query_scalar!(
-        r#"
-        SELECT
-            is_blacklisted
-        FROM
-            discord.users
-        WHERE
-            user_id = $1;"#,
-        id
-    )
-    .fetch_optional(pool)
-    .await
-    .unwrap_or(None);
+    if blacklist.resolve(blacklist.is_user_blacklisted(pool, &ctx.author().id).await)? {
+        return Err(CheckError::UserBlacklisted.into());
+    }
 
-    is_user_blacklisted.unwrap_or(false)
+    if let Some(guild_id) = ctx.guild_id() {
+        if blacklist.resolve(blacklist.is_guild_blacklisted(pool, &guild_id).await)? {
+            return Err(CheckError::GuildBlacklisted.into());
+        }
+    }
+    Ok(true)
 }
 
 async fn global_error_handler(error: poise::FrameworkError<'_, Data, CommandError>) {
@@ -149,8 +195,8 @@ async fn global_error_handler(error: poise::FrameworkError<'_, Data, CommandErro
 
     let reply = match error {
         // No response needed
-        FrameworkError::NotAnOwner { .. } => return (),
-        FrameworkError::UnknownCommand { .. } => return (),
+        FrameworkError::NotAnOwner { .. } => return,
+        FrameworkError::UnknownCommand { .. } => return,
         FrameworkError::DmOnly { ctx, .. } => error_reply(
             "DM only command",
             Some(&format!(
@@ -169,9 +215,8 @@ async fn global_error_handler(error: poise::FrameworkError<'_, Data, CommandErro
             missing_permissions,
             ..
         } => {
-            let message = if missing_permissions.is_some() {
+            let message = if let Some(missing_permissions) = missing_permissions {
                 missing_permissions
-                    .unwrap()
                     .get_permission_names()
                     .iter()
                     .map(|name| format!("`{name}`"))
@@ -182,6 +227,33 @@ async fn global_error_handler(error: poise::FrameworkError<'_, Data, CommandErro
             };
             error_reply("User missing permissions", Some(&message))
         }
+        FrameworkError::CommandCheckFailed {
+            error: Some(ref error),
+            ctx,
+            ..
+        } if error.is::<CheckError>() => match error.downcast_ref::<CheckError>() {
+            Some(CheckError::Cooldown(remaining_cooldown)) => {
+                cooldown_reply(ctx, *remaining_cooldown)
+            }
+            Some(CheckError::UserBlacklisted) => error_reply(
+                "You're blacklisted",
+                Some("You can't use BudBuddy. If this is a mistake, please contact support in the official support server"),
+            )
+            .components(vec![CreateActionRow::Buttons(vec![support_button])]),
+            Some(CheckError::GuildBlacklisted) => error_reply(
+                "This server is blacklisted",
+                Some("BudBuddy can't be used in this server. If this is a mistake, please contact support in the official support server"),
+            )
+            .components(vec![CreateActionRow::Buttons(vec![support_button])]),
+            Some(CheckError::BlacklistUnavailable) => error_reply(
+                "Something went wrong",
+                Some("BudBuddy can't check this command right now, please try again later"),
+            ),
+            _ => error_reply(
+                &format!("/{} is disabled", ctx.command().qualified_name),
+                Some("A server admin has turned this command off in this server"),
+            ),
+        },
         FrameworkError::CommandCheckFailed { ref error, .. } => {
             let mut title = String::from("Command Check Failed");
             if error.is_some() {
@@ -197,13 +269,7 @@ async fn global_error_handler(error: poise::FrameworkError<'_, Data, CommandErro
             remaining_cooldown,
             ctx,
             ..
-        } => error_reply(
-            &format!("/{} on cooldown", ctx.command().qualified_name),
-            Some(&format!(
-                "Please wait {:.1} seconds before trying again",
-                remaining_cooldown.as_secs_f32()
-            )),
-        ),
+        } => cooldown_reply(ctx, remaining_cooldown),
         FrameworkError::NsfwOnly { ctx, .. } => error_reply(
             "NSFW Only Command",
             Some(&format!(
@@ -263,7 +329,7 @@ async fn global_error_handler(error: poise::FrameworkError<'_, Data, CommandErro
                 ]);
             }
             let user = ctx.author();
-            embed = embed.author(CreateEmbedAuthor::new(&format!(
+            embed = embed.author(CreateEmbedAuthor::new(format!(
                 "{} - `{}`",
                 user.name, user.id
             )));
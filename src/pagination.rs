@@ -0,0 +1,176 @@
+use std::{future::Future, time::Duration};
+
+use poise::{
+    serenity_prelude::{
+        self as serenity, ButtonStyle, CreateActionRow, CreateButton, CreateEmbed,
+        CreateInteractionResponseMessage, ReactionType,
+    },
+    CreateReply, Modal,
+};
+
+use crate::types::{CommandError, CommandResult, Context};
+
+/// How long the buttons stay active after the last interaction
+const TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A single rendered page
+pub struct Page {
+    pub embed: CreateEmbed,
+    /// Total number of pages, so the buttons know where to stop
+    pub total_pages: u32,
+}
+
+#[derive(Debug, Modal)]
+#[name = "Jump to page"]
+struct JumpModal {
+    #[name = "Page"]
+    #[placeholder = "1"]
+    #[min_length = 1]
+    #[max_length = 6]
+    page: String,
+}
+
+/// Sends the first page and lets the author walk through the rest with
+/// previous, next and jump buttons. Pages are zero-indexed.
+pub async fn paginate<F, Fut>(ctx: Context<'_>, mut render_page: F) -> CommandResult
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<Page, CommandError>>,
+{
+    let mut current = 0;
+    let page = render_page(current).await?;
+    let mut total_pages = page.total_pages;
+
+    let previous_id = format!("{}-page-previous", ctx.id());
+    let jump_id = format!("{}-page-jump", ctx.id());
+    let next_id = format!("{}-page-next", ctx.id());
+
+    let buttons = |current: u32, total_pages: u32, disabled: bool| {
+        vec![CreateActionRow::Buttons(vec![
+            CreateButton::new(&previous_id)
+                .style(ButtonStyle::Secondary)
+                .emoji(ReactionType::Unicode("◀️".into()))
+                .disabled(disabled || current == 0),
+            CreateButton::new(&jump_id)
+                .style(ButtonStyle::Secondary)
+                .label(format!("{}/{}", current + 1, total_pages))
+                .disabled(disabled),
+            CreateButton::new(&next_id)
+                .style(ButtonStyle::Secondary)
+                .emoji(ReactionType::Unicode("▶️".into()))
+                .disabled(disabled || current + 1 >= total_pages),
+        ])]
+    };
+
+    let mut reply = CreateReply::default().embed(page.embed.clone());
+    if total_pages > 1 {
+        reply = reply.components(buttons(current, total_pages, false));
+    }
+    let handle = ctx.send(reply).await?;
+
+    if total_pages <= 1 {
+        return Ok(());
+    }
+
+    let mut embed = page.embed;
+    while let Some(button_interaction) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .custom_ids(vec![previous_id.clone(), jump_id.clone(), next_id.clone()])
+        .timeout(TIMEOUT)
+        .await
+    {
+        let custom_id = &button_interaction.data.custom_id;
+
+        let requested = if custom_id.eq(&previous_id) {
+            Some(current.saturating_sub(1))
+        } else if custom_id.eq(&next_id) {
+            Some(current + 1)
+        } else {
+            None
+        };
+
+        if let Some(requested) = requested {
+            current = requested.min(total_pages.saturating_sub(1));
+            let page = render_page(current).await?;
+            total_pages = page.total_pages.max(1);
+            current = current.min(total_pages - 1);
+            embed = page.embed;
+
+            button_interaction
+                .create_response(
+                    ctx,
+                    serenity::CreateInteractionResponse::UpdateMessage(
+                        CreateInteractionResponseMessage::default()
+                            .embed(embed.clone())
+                            .components(buttons(current, total_pages, false)),
+                    ),
+                )
+                .await?;
+            continue;
+        }
+
+        // Jump button, ask for a page number
+        let response = poise::execute_modal_on_component_interaction::<JumpModal>(
+            ctx,
+            button_interaction,
+            None,
+            Some(TIMEOUT),
+        )
+        .await?;
+
+        let Some(requested) = response.and_then(|r| r.page.trim().parse::<u32>().ok()) else {
+            continue;
+        };
+
+        current = requested.clamp(1, total_pages) - 1;
+        let page = render_page(current).await?;
+        total_pages = page.total_pages.max(1);
+        current = current.min(total_pages - 1);
+        embed = page.embed;
+
+        handle
+            .edit(
+                ctx,
+                CreateReply::default()
+                    .embed(embed.clone())
+                    .components(buttons(current, total_pages, false)),
+            )
+            .await?;
+    }
+
+    // Timed out, leave the last page but disable the buttons
+    handle
+        .edit(
+            ctx,
+            CreateReply::default()
+                .embed(embed)
+                .components(buttons(current, total_pages, true)),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Number of pages needed to show `total` items, `page_size` at a time
+pub fn page_count(total: i64, page_size: i64) -> u32 {
+    (total.max(0) as u64).div_ceil(page_size as u64).max(1) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_count_rounds_up() {
+        assert_eq!(page_count(1, 15), 1);
+        assert_eq!(page_count(15, 15), 1);
+        assert_eq!(page_count(16, 15), 2);
+        assert_eq!(page_count(45, 15), 3);
+    }
+
+    #[test]
+    fn page_count_is_at_least_one() {
+        assert_eq!(page_count(0, 15), 1);
+        assert_eq!(page_count(-3, 15), 1);
+    }
+}
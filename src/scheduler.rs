@@ -0,0 +1,222 @@
+//! Background tasks started once the bot is ready
+
+use std::{sync::Arc, time::Duration};
+
+use poise::serenity_prelude::{Channel, ChannelId, CreateEmbed, CreateMessage, Http};
+use sqlx::{
+    types::{time::Date, BigDecimal},
+    PgPool,
+};
+use tracing::{debug, info, warn};
+
+use crate::commands::strains::random_strain::daily_strain;
+
+/// How often guilds are checked for a due daily strain post
+const DAILY_STRAIN_INTERVAL: Duration = Duration::from_secs(60);
+/// How often expired data is deleted
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How long a guild's data is kept after the bot is removed from it
+const GUILD_PURGE_GRACE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Spawns every background task
+pub fn start(http: Arc<Http>, pool: PgPool) {
+    tokio::spawn(daily_strain_task(http, pool.clone()));
+    tokio::spawn(prune_task(pool));
+}
+
+async fn daily_strain_task(http: Arc<Http>, pool: PgPool) {
+    info!("Started daily strain scheduler");
+    let mut interval = tokio::time::interval(DAILY_STRAIN_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        post_daily_strains(&http, &pool)
+            .await
+            .inspect_err(|e| warn!("Failed to post daily strains: {}", e))
+            .ok();
+    }
+}
+
+/// Posts the strain of the day to every guild whose configured local time has passed today
+async fn post_daily_strains(http: &Http, pool: &PgPool) -> Result<(), anyhow::Error> {
+    // Local times come from `pg_timezone_names` offsets, so an unknown timezone falls back to UTC
+    // instead of failing the query for every guild
+    let due = sqlx::query_as!(
+        DueGuild,
+        r#"
+        SELECT
+            g.guild_id AS "guild_id!",
+            g.daily_strain_channel_id AS "channel_id!",
+            g.daily_strain_timezone AS "timezone!",
+            tz.name IS NOT NULL AS "known_timezone!",
+            local.now::DATE AS "today!"
+        FROM
+            discord.guilds g
+            LEFT JOIN pg_timezone_names tz ON tz.name = g.daily_strain_timezone
+            CROSS JOIN LATERAL (
+                SELECT (NOW() AT TIME ZONE 'UTC') + COALESCE(tz.utc_offset, INTERVAL '0') AS now
+            ) local
+        WHERE
+            g.daily_strain_channel_id IS NOT NULL
+            AND NOT (g.is_blacklisted AND (g.blacklist_expires_at IS NULL OR g.blacklist_expires_at > NOW()))
+            AND local.now::TIME >= g.daily_strain_time
+            AND (g.daily_strain_last_posted IS NULL OR g.daily_strain_last_posted < local.now::DATE);"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    if due.is_empty() {
+        return Ok(());
+    }
+
+    // Nothing is marked as posted, so it's tried again next time
+    let Some(embed) = daily_strain(pool).await? else {
+        warn!("No strain of the day available");
+        return Ok(());
+    };
+
+    debug!("Posting daily strain to {} guilds", due.len());
+    for guild in due {
+        if !guild.known_timezone {
+            warn!(
+                "Unknown daily strain timezone {} for guild {}, using UTC",
+                guild.timezone, guild.guild_id
+            );
+        }
+
+        post_daily_strain(http, pool, &guild, &embed)
+            .await
+            .inspect_err(|e| {
+                warn!(
+                    "Failed to post daily strain to guild {}: {}",
+                    guild.guild_id, e
+                )
+            })
+            .ok();
+    }
+    Ok(())
+}
+
+struct DueGuild {
+    guild_id: BigDecimal,
+    channel_id: BigDecimal,
+    timezone: String,
+    known_timezone: bool,
+    /// The guild's local date
+    today: Date,
+}
+
+/// Sends the strain of the day to the guild's channel, only marking it as posted once it's sent
+async fn post_daily_strain(
+    http: &Http,
+    pool: &PgPool,
+    guild: &DueGuild,
+    embed: &CreateEmbed,
+) -> Result<(), anyhow::Error> {
+    let channel_id = ChannelId::new(guild.channel_id.to_string().parse::<u64>()?);
+
+    // The channel may have stopped being age-restricted since it was configured
+    match channel_id.to_channel(http).await? {
+        Channel::Guild(channel) if channel.nsfw => {}
+        _ => {
+            info!("Skipped daily strain for {channel_id}, it's no longer age-restricted");
+            return Ok(());
+        }
+    }
+
+    channel_id
+        .send_message(http, CreateMessage::new().embed(embed.clone()))
+        .await?;
+
+    sqlx::query!(
+        "UPDATE discord.guilds SET daily_strain_last_posted = $2 WHERE guild_id = $1;",
+        guild.guild_id,
+        guild.today
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+async fn prune_task(pool: PgPool) {
+    info!("Started prune scheduler");
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+
+    loop {
+        interval.tick().await;
+        prune_expired(&pool)
+            .await
+            .inspect_err(|e| warn!("Failed to prune expired data: {}", e))
+            .ok();
+    }
+}
+
+/// Deletes puff reset snapshots that can no longer be undone, lifts expired blacklists, and
+/// deletes the data of guilds the bot was removed from more than `GUILD_PURGE_GRACE` ago
+async fn prune_expired(pool: &PgPool) -> Result<(), anyhow::Error> {
+    let pruned = sqlx::query!("DELETE FROM discord.puff_resets WHERE expires_at <= NOW();")
+        .execute(pool)
+        .await?
+        .rows_affected();
+
+    if pruned > 0 {
+        debug!("Pruned {} expired puff resets", pruned);
+    }
+
+    let lifted = sqlx::query!(
+        r#"
+        WITH users AS (
+            UPDATE discord.users
+            SET
+                is_blacklisted = FALSE,
+                blacklist_reason = NULL,
+                blacklisted_by = NULL,
+                blacklisted_at = NULL,
+                blacklist_expires_at = NULL
+            WHERE is_blacklisted AND blacklist_expires_at <= NOW()
+            RETURNING 1
+        ),
+        guilds AS (
+            UPDATE discord.guilds
+            SET
+                is_blacklisted = FALSE,
+                blacklist_reason = NULL,
+                blacklisted_by = NULL,
+                blacklisted_at = NULL,
+                blacklist_expires_at = NULL
+            WHERE is_blacklisted AND blacklist_expires_at <= NOW()
+            RETURNING 1
+        )
+        SELECT
+            (SELECT COUNT(*) FROM users) AS "users!",
+            (SELECT COUNT(*) FROM guilds) AS "guilds!";"#
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if lifted.users > 0 || lifted.guilds > 0 {
+        info!(
+            "Lifted expired blacklists of {} users and {} guilds",
+            lifted.users, lifted.guilds
+        );
+    }
+
+    // Blacklisted guilds are kept, so the blacklist still applies if they add the bot back.
+    // Member puffs cascade, and puff events keep their puff without the guild
+    let purged = sqlx::query!(
+        r#"
+        DELETE FROM discord.guilds
+        WHERE
+            removed_at <= NOW() - make_interval(secs => $1)
+            AND NOT (is_blacklisted AND (blacklist_expires_at IS NULL OR blacklist_expires_at > NOW()));"#,
+        GUILD_PURGE_GRACE.as_secs_f64()
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    if purged > 0 {
+        info!("Purged data of {} removed guilds", purged);
+    }
+    Ok(())
+}
@@ -1,16 +1,24 @@
+use std::{fmt, time::Duration};
+
 use poise::serenity_prelude::ChannelId;
 use sqlx::{
     postgres::{PgConnectOptions, PgSslMode},
     PgPool,
 };
-use tracing::{debug, error, instrument, warn};
+use tracing::{debug, error, info, instrument, warn};
 
-use crate::unwrap_env_var;
+use crate::{
+    blacklist::{BlacklistCache, FailurePolicy},
+    unwrap_env_var, user_data,
+};
 
 #[derive(Debug)]
 pub struct Data {
     pub pool: PgPool,
     pub error_channel: Option<ChannelId>,
+    pub blacklist: BlacklistCache,
+    /// Leave blacklisted guilds when joining them, or when they're loaded on startup
+    pub leave_blacklisted_guilds: bool,
 }
 
 #[instrument]
@@ -32,8 +40,19 @@ async fn connect_to_db() -> PgPool {
     }
 }
 
+#[instrument(skip(pool))]
+async fn run_migrations(pool: &PgPool) {
+    info!("Running database migrations...");
+
+    if let Err(error) = sqlx::migrate!().run(pool).await {
+        error!("sqlx::migrate::MigrateError::{:?}", error);
+        std::process::exit(1)
+    }
+}
+
 impl Data {
-    /// Panics if it cannot connect to the database from the environment variables
+    /// Exits if it cannot connect to the database from the environment variables,
+    /// or if the migrations in `migrations/` fail to apply
     pub async fn new() -> Self {
         let mut channel: Option<ChannelId> = None;
         if let Ok(id) = std::env::var("ERROR_CHANNEL_ID") {
@@ -43,13 +62,52 @@ impl Data {
         } else {
             warn!("ERROR_CHANNEL_ID not set");
         }
+        let leave_blacklisted_guilds = std::env::var("LEAVE_BLACKLISTED_GUILDS")
+            .is_ok_and(|value| value.eq_ignore_ascii_case("true") || value == "1");
+
+        let pool = connect_to_db().await;
+        run_migrations(&pool).await;
+        user_data::warn_unregistered_tables(&pool).await;
+
         Self {
-            pool: connect_to_db().await,
+            pool,
             error_channel: channel,
+            blacklist: BlacklistCache::new(FailurePolicy::from_env()),
+            leave_blacklisted_guilds,
         }
     }
 }
 
+/// Returned by command checks that need their own reply in `global_error_handler`,
+/// instead of the generic "Command Check Failed" one
+#[derive(Debug)]
+pub enum CheckError {
+    /// Same as [`poise::FrameworkError::CooldownHit`], for cooldowns that aren't static
+    Cooldown(Duration),
+    /// The command was turned off in this guild
+    Disabled,
+    UserBlacklisted,
+    GuildBlacklisted,
+    /// The blacklist couldn't be read, and `BLACKLIST_FAILURE_POLICY` is closed
+    BlacklistUnavailable,
+}
+
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cooldown(remaining) => {
+                write!(f, "On cooldown for {:.1} seconds", remaining.as_secs_f32())
+            }
+            Self::Disabled => write!(f, "Disabled in this guild"),
+            Self::UserBlacklisted => write!(f, "User is blacklisted"),
+            Self::GuildBlacklisted => write!(f, "Guild is blacklisted"),
+            Self::BlacklistUnavailable => write!(f, "Blacklist is unavailable"),
+        }
+    }
+}
+
+impl std::error::Error for CheckError {}
+
 pub type CommandError = anyhow::Error;
 pub type Command = poise::Command<Data, CommandError>;
 pub type CommandResult = Result<(), CommandError>;
@@ -0,0 +1,251 @@
+//! Every table that stores data about a Discord user, used by `/privacy`.
+//!
+//! New tables with a `user_id` column must be added to [`USER_TABLES`], otherwise they're left out
+//! of data exports and deletions. [`warn_unregistered_tables`] logs any that were missed on startup.
+
+use poise::serenity_prelude::UserId;
+use sqlx::{types::BigDecimal, PgConnection, PgExecutor, PgPool};
+use tracing::warn;
+
+/// A table with rows belonging to a user, found by its `user_id` column
+pub struct UserTable {
+    /// Schema qualified name, also used as the key in exports
+    pub name: &'static str,
+    /// Column the exported rows are sorted by
+    pub order_by: &'static str,
+    /// Whether `/privacy delete` removes the user's rows
+    pub deletable: bool,
+}
+
+pub const USER_TABLES: &[UserTable] = &[
+    UserTable {
+        name: "discord.users",
+        order_by: "user_id",
+        deletable: true,
+    },
+    UserTable {
+        name: "discord.guild_members",
+        order_by: "guild_id",
+        deletable: true,
+    },
+    UserTable {
+        name: "discord.puff_events",
+        order_by: "created_at",
+        deletable: true,
+    },
+    UserTable {
+        name: "discord.user_achievements",
+        order_by: "earned_at",
+        deletable: true,
+    },
+    UserTable {
+        name: "discord.puff_resets",
+        order_by: "reset_at",
+        deletable: true,
+    },
+    // Kept after deletion, so opting out sticks and deletions stay auditable
+    UserTable {
+        name: "discord.opted_out_users",
+        order_by: "opted_out_at",
+        deletable: false,
+    },
+    UserTable {
+        name: "discord.privacy_audit_log",
+        order_by: "created_at",
+        deletable: false,
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrivacyAction {
+    Delete,
+    OptOut,
+    OptIn,
+}
+
+impl PrivacyAction {
+    /// Stored in `discord.privacy_audit_log.action`, so these must never change
+    pub fn id(&self) -> &'static str {
+        match self {
+            Self::Delete => "delete",
+            Self::OptOut => "opt_out",
+            Self::OptIn => "opt_in",
+        }
+    }
+}
+
+/// Every row stored about the user in [`USER_TABLES`], as a pretty printed JSON document.
+///
+/// Built in Postgres so snowflake IDs keep their full precision
+pub async fn export(pool: &PgPool, user_id: &UserId) -> Result<String, sqlx::Error> {
+    let tables = USER_TABLES
+        .iter()
+        .map(|table| {
+            format!(
+                "'{name}', (SELECT COALESCE(jsonb_agg(t ORDER BY t.{order_by}), '[]') FROM {name} t WHERE t.user_id = $1)",
+                name = table.name,
+                order_by = table.order_by
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",\n");
+
+    // Table names come from `USER_TABLES`, never from user input
+    let query = format!(
+        r#"
+        SELECT jsonb_pretty(jsonb_build_object(
+            'user_id', $1::TEXT,
+            'exported_at', NOW(),
+            'tables', jsonb_build_object({tables})
+        ));"#
+    );
+
+    sqlx::query_scalar(&query)
+        .bind(BigDecimal::from(user_id.get()))
+        .fetch_one(pool)
+        .await
+}
+
+/// Removes the user's rows from every deletable table in [`USER_TABLES`].
+///
+/// Blacklisted users keep a `discord.users` row with only their blacklist details set
+async fn delete_rows(conn: &mut PgConnection, user_id: &UserId) -> Result<(), sqlx::Error> {
+    let id = BigDecimal::from(user_id.get());
+
+    // `discord.users` goes last, as the other tables reference it
+    for table in USER_TABLES
+        .iter()
+        .filter(|t| t.deletable && t.name != "discord.users")
+    {
+        // Table names come from `USER_TABLES`, never from user input
+        sqlx::query(&format!("DELETE FROM {} WHERE user_id = $1;", table.name))
+            .bind(&id)
+            .execute(&mut *conn)
+            .await?;
+    }
+
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM discord.users
+        WHERE user_id = $1
+        RETURNING
+            is_blacklisted,
+            blacklist_reason,
+            blacklisted_by,
+            blacklisted_at,
+            blacklist_expires_at;"#,
+        id
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(blacklist) = deleted.filter(|d| d.is_blacklisted) {
+        sqlx::query!(
+            r#"
+            INSERT INTO discord.users (
+                user_id,
+                is_blacklisted,
+                blacklist_reason,
+                blacklisted_by,
+                blacklisted_at,
+                blacklist_expires_at
+            )
+            VALUES ($1, TRUE, $2, $3, $4, $5);"#,
+            id,
+            blacklist.blacklist_reason,
+            blacklist.blacklisted_by,
+            blacklist.blacklisted_at,
+            blacklist.blacklist_expires_at
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// Deletes everything stored about the user, see [`delete_rows`]
+pub async fn delete_user_data(pool: &PgPool, user_id: &UserId) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    delete_rows(&mut tx, user_id).await?;
+    log_action(&mut *tx, user_id, PrivacyAction::Delete).await?;
+    tx.commit().await
+}
+
+/// Deletes the user's data and stops `insert_user` from creating a row for them again
+pub async fn opt_out(pool: &PgPool, user_id: &UserId) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    delete_rows(&mut tx, user_id).await?;
+    sqlx::query!(
+        "INSERT INTO discord.opted_out_users (user_id) VALUES ($1) ON CONFLICT DO NOTHING;",
+        BigDecimal::from(user_id.get())
+    )
+    .execute(&mut *tx)
+    .await?;
+    log_action(&mut *tx, user_id, PrivacyAction::OptOut).await?;
+    tx.commit().await
+}
+
+/// Lets `insert_user` create a row for the user again. Returns whether they had opted out
+pub async fn opt_in(pool: &PgPool, user_id: &UserId) -> Result<bool, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let opted_out = sqlx::query!(
+        "DELETE FROM discord.opted_out_users WHERE user_id = $1;",
+        BigDecimal::from(user_id.get())
+    )
+    .execute(&mut *tx)
+    .await?
+    .rows_affected()
+        > 0;
+
+    if opted_out {
+        log_action(&mut *tx, user_id, PrivacyAction::OptIn).await?;
+    }
+    tx.commit().await?;
+    Ok(opted_out)
+}
+
+pub async fn is_opted_out(
+    executor: impl PgExecutor<'_>,
+    user_id: &UserId,
+) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM discord.opted_out_users WHERE user_id = $1) AS "exists!";"#,
+        BigDecimal::from(user_id.get())
+    )
+    .fetch_one(executor)
+    .await
+}
+
+async fn log_action(
+    executor: impl PgExecutor<'_>,
+    user_id: &UserId,
+    action: PrivacyAction,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "INSERT INTO discord.privacy_audit_log (user_id, action) VALUES ($1, $2);",
+        BigDecimal::from(user_id.get()),
+        action.id()
+    )
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Logs every `discord` table with a `user_id` column that's missing from [`USER_TABLES`]
+pub async fn warn_unregistered_tables(pool: &PgPool) {
+    let tables = sqlx::query_scalar!(
+        r#"
+        SELECT table_schema || '.' || table_name AS "name!"
+        FROM information_schema.columns
+        WHERE table_schema = 'discord' AND column_name = 'user_id';"#
+    )
+    .fetch_all(pool)
+    .await
+    .unwrap_or_default();
+
+    for table in tables {
+        if !USER_TABLES.iter().any(|t| t.name == table) {
+            warn!("{table} stores user data but isn't registered in user_data::USER_TABLES");
+        }
+    }
+}